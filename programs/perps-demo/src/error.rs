@@ -26,4 +26,64 @@ pub enum PerpetualsError {
     MaxLeverage,
     #[msg("Token is not supported")]
     UnsupportedToken,
+    #[msg("Invalid collateral custody")]
+    InvalidCollateralCustody,
+    #[msg("Invalid position state")]
+    InvalidPositionState,
+    #[msg("Order trigger price is not reached")]
+    OrderNotTriggered,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Position trigger price is not reached")]
+    PositionNotTriggered,
+    #[msg("Transaction deadline exceeded")]
+    DeadlineExceeded,
+    #[msg("Custody liabilities are covered by its assets")]
+    AutoDeleverageNotRequired,
+    #[msg("Position is not next in the auto-deleveraging queue")]
+    InvalidAdlPosition,
+    #[msg("Collateral doesn't cover position fees")]
+    InsufficientCollateral,
+    #[msg("Open interest limit exceeded")]
+    MaxOpenInterest,
+    #[msg("Position size is below the minimum")]
+    MinPositionSize,
+    #[msg("Position size exceeds the maximum")]
+    MaxPositionSize,
+    #[msg("Position collateral is below the minimum")]
+    MinCollateral,
+    #[msg("Custody utilization limit exceeded")]
+    MaxUtilization,
+    #[msg("Pool AUM limit exceeded")]
+    MaxPoolAum,
+    #[msg("Custody deposit limit exceeded")]
+    MaxCustodyAmount,
+    #[msg("Not enough unlocked liquidity in custody")]
+    InsufficientLiquidity,
+    #[msg("Account is not authorized to sign this instruction")]
+    MultisigAccountNotAuthorized,
+    #[msg("Account has already signed this instruction")]
+    MultisigAlreadySigned,
+    #[msg("Signer doesn't hold the required role")]
+    MissingRole,
+    #[msg("Swaps are paused (allow_swap)")]
+    SwapNotAllowed,
+    #[msg("Adding liquidity is paused (allow_add_liquidity)")]
+    AddLiquidityNotAllowed,
+    #[msg("Removing liquidity is paused (allow_remove_liquidity)")]
+    RemoveLiquidityNotAllowed,
+    #[msg("Opening positions is paused (allow_open_position)")]
+    OpenPositionNotAllowed,
+    #[msg("Closing positions is paused (allow_close_position)")]
+    ClosePositionNotAllowed,
+    #[msg("Liquidations are paused (allow_liquidation)")]
+    LiquidationNotAllowed,
+    #[msg("Custody still holds funds or open positions")]
+    CustodyNotEmpty,
+    #[msg("Pool still has tokens or LP supply")]
+    PoolNotEmpty,
+    #[msg("Pending change can't be applied before its execute time")]
+    TimelockNotExpired,
+    #[msg("Account is already at the current version")]
+    AccountAlreadyMigrated,
 }
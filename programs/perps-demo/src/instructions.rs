@@ -1,29 +1,90 @@
 // admin instructions
+pub mod accept_admin;
 pub mod add_pool;
 pub mod add_token;
+pub mod cancel_admin_proposal;
+pub mod cancel_pending_change;
 pub mod init;
+pub mod migrate_custody;
+pub mod migrate_perpetuals;
+pub mod migrate_pool;
+pub mod migrate_position;
+pub mod propose_admin;
+pub mod remove_pool;
+pub mod remove_token;
+pub mod set_admin_signers;
+pub mod set_custody_config;
+pub mod set_deposit_caps;
+pub mod set_permissions;
+pub mod set_role;
+pub mod set_timelock_delay;
+pub mod top_up_insurance_fund;
+pub mod withdraw_insurance_fund;
 
 // test instructions
 pub mod set_test_oracle_price;
 
+// views
+pub mod get_adl_queue;
+pub mod get_audit_log;
+
 // public instructions
+pub mod add_collateral;
 pub mod add_liquidity;
+pub mod apply_pending_change;
+pub mod auto_deleverage;
+pub mod cancel_order;
 pub mod close_position;
+pub mod create_order;
+pub mod execute_order;
+pub mod execute_trigger;
 pub mod liquidate;
 pub mod open_position;
+pub mod remove_collateral;
 pub mod remove_liquidity;
+pub mod set_trigger;
 pub mod swap;
 
 // bring everything in scope
+pub use accept_admin::*;
 pub use add_pool::*;
 pub use add_token::*;
+pub use cancel_admin_proposal::*;
+pub use cancel_pending_change::*;
 pub use init::*;
+pub use migrate_custody::*;
+pub use migrate_perpetuals::*;
+pub use migrate_pool::*;
+pub use migrate_position::*;
+pub use propose_admin::*;
+pub use remove_pool::*;
+pub use remove_token::*;
+pub use set_admin_signers::*;
+pub use set_custody_config::*;
+pub use set_deposit_caps::*;
+pub use set_permissions::*;
+pub use set_role::*;
+pub use set_timelock_delay::*;
+pub use top_up_insurance_fund::*;
+pub use withdraw_insurance_fund::*;
 
 pub use set_test_oracle_price::*;
 
+pub use get_adl_queue::*;
+pub use get_audit_log::*;
+
+pub use add_collateral::*;
 pub use add_liquidity::*;
+pub use apply_pending_change::*;
+pub use auto_deleverage::*;
+pub use cancel_order::*;
 pub use close_position::*;
+pub use create_order::*;
+pub use execute_order::*;
+pub use execute_trigger::*;
 pub use liquidate::*;
 pub use open_position::*;
+pub use remove_collateral::*;
 pub use remove_liquidity::*;
+pub use set_trigger::*;
 pub use swap::*;
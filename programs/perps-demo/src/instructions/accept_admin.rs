@@ -0,0 +1,73 @@
+//! AcceptAdmin instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::Multisig,
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub pending_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        has_one = pending_admin,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AcceptAdminParams {}
+
+#[event]
+pub struct AdminChangedEvent {
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+}
+
+pub fn accept_admin(ctx: Context<AcceptAdmin>, _params: &AcceptAdminParams) -> Result<()> {
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+
+    emit!(AdminChangedEvent {
+        previous_admin: perpetuals.admin,
+        admin: perpetuals.pending_admin,
+    });
+
+    // the admin holds a multisig seat, it passes to the new admin
+    ctx.accounts
+        .multisig
+        .replace_signer(&perpetuals.admin, &perpetuals.pending_admin)?;
+
+    perpetuals.admin = perpetuals.pending_admin;
+    perpetuals.pending_admin = Pubkey::default();
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::AcceptAdmin,
+        ctx.accounts.pending_admin.key(),
+        &ctx.accounts.perpetuals.admin,
+    )?;
+
+    Ok(())
+}
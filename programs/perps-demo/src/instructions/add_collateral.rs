@@ -0,0 +1,190 @@
+//! AddCollateral instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: AddCollateralParams)]
+pub struct AddCollateral<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = collateral_custody,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddCollateralParams {
+    collateral: u64,
+    deadline: Option<i64>,
+}
+
+#[event]
+pub struct AddCollateralEvent {
+    pub position: Pubkey,
+    pub collateral_usd: u64,
+    pub leverage: u64,
+}
+
+pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::OpenPosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+    if params.collateral == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(position.size_usd > 0, PerpetualsError::InvalidPositionState);
+
+    // compute collateral value
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_usd = collateral_token_price
+        .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
+    msg!("Collateral added in USD: {}", collateral_usd);
+
+    // update position
+    msg!("Update position");
+    position.time = curtime;
+    position.collateral_usd = math::checked_add(position.collateral_usd, collateral_usd)?;
+    position.collateral_amount = math::checked_add(position.collateral_amount, params.collateral)?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.collateral,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    collateral_custody.assets.collateral =
+        math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
+
+    let leverage = pool.get_leverage(position, &token_price)?;
+    msg!("New leverage: {}", leverage);
+
+    emit!(AddCollateralEvent {
+        position: position.key(),
+        collateral_usd: position.collateral_usd,
+        leverage,
+    });
+
+    Ok(())
+}
@@ -2,8 +2,14 @@
 
 use {
     crate::{
+        error::PerpetualsError,
         math,
-        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Mint, Token, TokenAccount},
@@ -92,11 +98,18 @@ pub struct AddLiquidity<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddLiquidityParams {
     amount: u64,
+    deadline: Option<i64>,
 }
 
 pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) -> Result<()> {
     // validate inputs
     msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::AddLiquidity,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody],
+    )?;
     if params.amount == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
@@ -130,6 +143,18 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
 
     // compute amount of lp tokens to mint
     let token_amount_usd = token_price.get_asset_amount_usd(params.amount, custody.decimals)?;
+
+    // check deposit caps
+    require_gte!(
+        pool.max_aum_usd,
+        math::checked_add(pool_amount_usd, token_amount_usd as u128)?,
+        PerpetualsError::MaxPoolAum
+    );
+    require_gte!(
+        custody.max_amount,
+        math::checked_add(custody.assets.owned, params.amount)?,
+        PerpetualsError::MaxCustodyAmount
+    );
     let lp_amount = if pool_amount_usd == 0 {
         token_amount_usd
     } else {
@@ -1,7 +1,12 @@
 //! AddPool instruction handler
 
 use {
-    crate::state::{perpetuals::Perpetuals, pool::Pool},
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::{Permissions, Perpetuals},
+        pool::Pool,
+    },
     anchor_lang::prelude::*,
     anchor_spl::token::{Mint, Token},
 };
@@ -12,6 +17,13 @@ pub struct AddPool<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -21,7 +33,6 @@ pub struct AddPool<'info> {
 
     #[account(
         mut,
-        has_one = admin,
         realloc = Perpetuals::LEN + (perpetuals.pools.len() + 1) * std::mem::size_of::<Pubkey>(),
         realloc::payer = admin,
         realloc::zero = false,
@@ -31,7 +42,7 @@ pub struct AddPool<'info> {
     pub perpetuals: Box<Account<'info, Perpetuals>>,
 
     #[account(
-        init,
+        init_if_needed,
         payer = admin,
         space = Pool::LEN,
         seeds = [b"pool",
@@ -41,7 +52,7 @@ pub struct AddPool<'info> {
     pub pool: Box<Account<'info, Pool>>,
 
     #[account(
-        init,
+        init_if_needed,
         payer = admin,
         mint::authority = transfer_authority,
         mint::freeze_authority = transfer_authority,
@@ -52,6 +63,13 @@ pub struct AddPool<'info> {
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
 
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
     rent: Sysvar<'info, Rent>,
@@ -60,9 +78,26 @@ pub struct AddPool<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddPoolParams {
     pub name: String,
+    pub max_aum_usd: u128,
 }
 
-pub fn add_pool(ctx: Context<AddPool>, params: &AddPoolParams) -> Result<()> {
+pub fn add_pool(ctx: Context<AddPool>, params: &AddPoolParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::AddPool, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
     // validate inputs
     if params.name.is_empty() {
         return Err(ProgramError::InvalidArgument.into());
@@ -71,7 +106,14 @@ pub fn add_pool(ctx: Context<AddPool>, params: &AddPoolParams) -> Result<()> {
     // record pool data
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     let pool = ctx.accounts.pool.as_mut();
+    if !pool.name.is_empty() {
+        // return error if pool is already initialized
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
     pool.name = params.name.clone();
+    pool.max_aum_usd = params.max_aum_usd;
+    pool.permissions = Permissions::default();
+    pool.version = Pool::VERSION;
     pool.bump = *ctx.bumps.get("pool").ok_or(ProgramError::InvalidSeeds)?;
     pool.lp_token_bump = *ctx
         .bumps
@@ -80,5 +122,10 @@ pub fn add_pool(ctx: Context<AddPool>, params: &AddPoolParams) -> Result<()> {
 
     perpetuals.pools.push(ctx.accounts.pool.key());
 
-    Ok(())
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::AddPool, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
 }
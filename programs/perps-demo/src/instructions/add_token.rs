@@ -4,13 +4,16 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::{Custody, OracleParams, PricingParams},
-            perpetuals::Perpetuals,
+            audit_log::{AuditAction, AuditLog},
+            custody::{Custody, Fees, OracleParams, PricingParams},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::{Permissions, Perpetuals},
             pool::{Pool, PoolToken},
         },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
 };
 
 #[derive(Accounts)]
@@ -18,6 +21,13 @@ pub struct AddToken<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -26,7 +36,6 @@ pub struct AddToken<'info> {
     pub transfer_authority: AccountInfo<'info>,
 
     #[account(
-        has_one = admin,
         seeds = [b"perpetuals"],
         bump = perpetuals.perpetuals_bump
     )]
@@ -66,9 +75,28 @@ pub struct AddToken<'info> {
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = custody_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
     #[account()]
     pub custody_token_mint: Box<Account<'info, Mint>>,
 
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
     rent: Sysvar<'info, Rent>,
@@ -76,37 +104,72 @@ pub struct AddToken<'info> {
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddTokenParams {
+    pub is_stable: bool,
     pub oracle: OracleParams,
     pub pricing: PricingParams,
+    pub fees: Fees,
+    pub max_amount: u64,
 }
 
-pub fn add_token(ctx: Context<AddToken>, params: &AddTokenParams) -> Result<()> {
+pub fn add_token(ctx: Context<AddToken>, params: &AddTokenParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::AddToken, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    if ctx.accounts.custody.mint != Pubkey::default() {
+        // existing custodies are updated with set_custody_config
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
+
     // update pool data
     let pool = ctx.accounts.pool.as_mut();
-    if let Ok(idx) = pool.get_token_id(&ctx.accounts.custody.key()) {
-        pool.tokens[idx].custody = ctx.accounts.custody.key();
-    } else {
-        pool.tokens.push(PoolToken {
-            custody: ctx.accounts.custody.key(),
-        });
-    }
+    pool.tokens.push(PoolToken {
+        custody: ctx.accounts.custody.key(),
+    });
 
     // record custody data
     let custody = ctx.accounts.custody.as_mut();
     custody.token_account = ctx.accounts.custody_token_account.key();
     custody.mint = ctx.accounts.custody_token_mint.key();
     custody.decimals = ctx.accounts.custody_token_mint.decimals;
+    custody.is_stable = params.is_stable;
     custody.oracle = params.oracle;
     custody.pricing = params.pricing;
+    custody.fees = params.fees;
+    custody.max_amount = params.max_amount;
+    custody.permissions = Permissions::default();
+    custody.version = Custody::VERSION;
     custody.bump = *ctx.bumps.get("custody").ok_or(ProgramError::InvalidSeeds)?;
     custody.token_account_bump = *ctx
         .bumps
         .get("custody_token_account")
         .ok_or(ProgramError::InvalidSeeds)?;
+    custody.insurance_fund_bump = *ctx
+        .bumps
+        .get("insurance_fund_token_account")
+        .ok_or(ProgramError::InvalidSeeds)?;
 
     if !custody.validate() {
-        err!(PerpetualsError::InvalidTokenConfig)
-    } else {
-        Ok(())
+        return err!(PerpetualsError::InvalidTokenConfig);
     }
+
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::AddToken, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
 }
@@ -0,0 +1,155 @@
+//! ApplyPendingChange instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            custody::{Custody, CustodyConfig},
+            pending_change::{ChangeType, DepositCaps, PendingChange},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct ApplyPendingChange<'info> {
+    #[account()]
+    pub executor: Signer<'info>,
+
+    /// CHECK: account that queued the change, receives the pending change account rent
+    #[account(
+        mut,
+        constraint = payer.key() == pending_change.payer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_change",
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[pending_change.change_type as u8]],
+        bump = pending_change.bump,
+        has_one = custody,
+        close = payer
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ApplyPendingChangeParams {}
+
+#[event]
+pub struct CustodyConfigChangedEvent {
+    pub custody: Pubkey,
+    pub old_config: CustodyConfig,
+    pub new_config: CustodyConfig,
+}
+
+#[event]
+pub struct DepositCapsChangedEvent {
+    pub custody: Pubkey,
+    pub old_caps: DepositCaps,
+    pub new_caps: DepositCaps,
+}
+
+pub fn apply_pending_change(
+    ctx: Context<ApplyPendingChange>,
+    _params: &ApplyPendingChangeParams,
+) -> Result<()> {
+    // check timelock
+    msg!("Check timelock");
+    let pending_change = ctx.accounts.pending_change.as_ref();
+    require_gte!(
+        ctx.accounts.perpetuals.get_time()?,
+        pending_change.execute_time,
+        PerpetualsError::TimelockNotExpired
+    );
+
+    match pending_change.change_type {
+        ChangeType::CustodyConfig => {
+            // update custody data
+            msg!("Update custody config");
+            let custody = ctx.accounts.custody.as_mut();
+            let old_config = custody.get_config();
+            custody.set_config(&pending_change.config);
+
+            if !custody.validate() {
+                return err!(PerpetualsError::InvalidTokenConfig);
+            }
+
+            emit!(CustodyConfigChangedEvent {
+                custody: custody.key(),
+                old_config,
+                new_config: pending_change.config,
+            });
+
+            // record audit log entry
+            ctx.accounts.audit_log.record(
+                AuditAction::ApplyPendingChange,
+                ctx.accounts.executor.key(),
+                &pending_change.config,
+            )?;
+        }
+        ChangeType::DepositCaps => {
+            // update deposit caps
+            msg!("Update deposit caps");
+            let pool = ctx.accounts.pool.as_mut();
+            let custody = ctx.accounts.custody.as_mut();
+            let old_caps = DepositCaps {
+                max_aum_usd: pool.max_aum_usd,
+                max_amount: custody.max_amount,
+            };
+            pool.max_aum_usd = pending_change.deposit_caps.max_aum_usd;
+            custody.max_amount = pending_change.deposit_caps.max_amount;
+
+            emit!(DepositCapsChangedEvent {
+                custody: custody.key(),
+                old_caps,
+                new_caps: pending_change.deposit_caps,
+            });
+
+            // record audit log entry
+            ctx.accounts.audit_log.record(
+                AuditAction::ApplyPendingChange,
+                ctx.accounts.executor.key(),
+                &pending_change.deposit_caps,
+            )?;
+        }
+    }
+
+    Ok(())
+}
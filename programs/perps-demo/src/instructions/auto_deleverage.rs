@@ -0,0 +1,257 @@
+//! AutoDeleverage instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals, Role},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct AutoDeleverage<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.owner == position.owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: position owner, receives the position account rent
+    #[account(
+        mut,
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint = perpetuals.has_role(keeper.key, Role::Keeper) @ PerpetualsError::MissingRole,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = collateral_custody,
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   candidate positions of the custody side, including the target (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AutoDeleverageParams {}
+
+#[event]
+pub struct AutoDeleverageEvent {
+    pub position: Pubkey,
+    pub size_usd: u64,
+    pub amount_out: u64,
+}
+
+pub fn auto_deleverage(ctx: Context<AutoDeleverage>, _params: &AutoDeleverageParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    ctx.accounts.perpetuals.check_permission(
+        Permission::ClosePosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    require!(
+        ctx.accounts.position.size_usd > 0,
+        PerpetualsError::InvalidPositionState
+    );
+
+    // compute exit price
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        ctx.accounts.custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        ctx.accounts.custody.oracle.max_price_error,
+        ctx.accounts.custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        ctx.accounts.collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        ctx.accounts.collateral_custody.oracle.max_price_error,
+        ctx.accounts.collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    // check custody liabilities and position rank
+    msg!("Check ADL queue");
+    let queue = pool.get_adl_queue(
+        ctx.remaining_accounts,
+        ctx.accounts.position.side,
+        &ctx.accounts.custody,
+        &ctx.accounts.collateral_custody,
+        &token_price,
+        &collateral_token_price,
+    )?;
+    msg!(
+        "Liabilities: {}, assets: {}",
+        queue.liabilities,
+        queue.assets
+    );
+    require_gt!(
+        queue.liabilities,
+        queue.assets,
+        PerpetualsError::AutoDeleverageNotRequired
+    );
+    let entry = queue
+        .positions
+        .first()
+        .ok_or(PerpetualsError::InvalidAdlPosition)?;
+    require_keys_eq!(
+        entry.position,
+        ctx.accounts.position.key(),
+        PerpetualsError::InvalidAdlPosition
+    );
+
+    // close enough size for its profit to cover the shortfall, that profit is cut
+    // by the shortfall so liabilities drop by more than assets
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let shortfall_usd = collateral_token_price.get_asset_amount_usd(
+        math::checked_sub(queue.liabilities, queue.assets)?,
+        collateral_custody.decimals,
+    )?;
+    let size_usd = if shortfall_usd >= entry.profit_usd {
+        position.size_usd
+    } else {
+        std::cmp::min(
+            position.size_usd,
+            math::checked_add(
+                math::checked_as_u64(math::checked_div(
+                    math::checked_mul(position.size_usd as u128, shortfall_usd as u128)?,
+                    entry.profit_usd as u128,
+                )?)?,
+                1,
+            )?,
+        )
+    };
+    let size_usd = pool.get_close_size(position, custody, size_usd)?;
+    let transfer_amount = pool.decrease_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        size_usd,
+        shortfall_usd,
+    )?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    emit!(AutoDeleverageEvent {
+        position: position.key(),
+        size_usd,
+        amount_out: transfer_amount,
+    });
+
+    // update position
+    if position.size_usd == 0 {
+        msg!("Close position");
+        **position = Position::default();
+        ctx.accounts
+            .position
+            .close(ctx.accounts.owner.to_account_info())?;
+    } else {
+        position.time = curtime;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! CancelAdminProposal instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct CancelAdminProposal<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelAdminProposalParams {}
+
+#[event]
+pub struct AdminProposalCancelledEvent {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+pub fn cancel_admin_proposal(
+    ctx: Context<CancelAdminProposal>,
+    params: &CancelAdminProposalParams,
+) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::CancelAdminProposal, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    if perpetuals.pending_admin == Pubkey::default() {
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+
+    emit!(AdminProposalCancelledEvent {
+        admin: perpetuals.admin,
+        pending_admin: perpetuals.pending_admin,
+    });
+
+    perpetuals.pending_admin = Pubkey::default();
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::CancelAdminProposal,
+        ctx.accounts.admin.key(),
+        params,
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,99 @@
+//! CancelOrder instruction handler
+
+use {
+    crate::{
+        math,
+        state::{order::Order, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == order_token_account.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 order.custody.as_ref(),
+                 &[order.side as u8],
+                 &order.order_id.to_le_bytes()],
+        bump = order.bump,
+        close = owner
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [b"order_token_account",
+                 order.key().as_ref()],
+        bump = order.token_account_bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelOrderParams {
+    deadline: Option<i64>,
+}
+
+pub fn cancel_order(ctx: Context<CancelOrder>, params: &CancelOrderParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let order = ctx.accounts.order.as_mut();
+
+    // return escrowed collateral and execution fee
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_add(order.collateral, order.execution_fee)?,
+    )?;
+
+    perpetuals.close_token_account(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
+
+    Ok(())
+}
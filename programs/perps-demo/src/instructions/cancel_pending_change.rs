@@ -0,0 +1,109 @@
+//! CancelPendingChange instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+        pending_change::{ChangeType, PendingChange},
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+};
+
+#[derive(Accounts)]
+pub struct CancelPendingChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    /// CHECK: account that queued the change, receives the pending change account rent
+    #[account(
+        mut,
+        constraint = payer.key() == pending_change.payer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    // custody is not required, changes queued for removed custodies can still be cancelled
+    #[account(
+        mut,
+        seeds = [b"pending_change",
+                 pending_change.pool.as_ref(),
+                 pending_change.custody.as_ref(),
+                 &[pending_change.change_type as u8]],
+        bump = pending_change.bump
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelPendingChangeParams {}
+
+pub fn cancel_pending_change(
+    ctx: Context<CancelPendingChange>,
+    params: &CancelPendingChangeParams,
+) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::CancelPendingChange, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    let pending_change = ctx.accounts.pending_change.as_ref();
+    msg!(
+        "Cancel pending {:?} change for custody {}",
+        pending_change.change_type,
+        pending_change.custody
+    );
+
+    // record audit log entry
+    match pending_change.change_type {
+        ChangeType::CustodyConfig => ctx.accounts.audit_log.record(
+            AuditAction::CancelPendingChange,
+            ctx.accounts.admin.key(),
+            &pending_change.config,
+        )?,
+        ChangeType::DepositCaps => ctx.accounts.audit_log.record(
+            AuditAction::CancelPendingChange,
+            ctx.accounts.admin.key(),
+            &pending_change.deposit_caps,
+        )?,
+    }
+
+    // close pending change account
+    **ctx.accounts.pending_change = PendingChange::default();
+    ctx.accounts
+        .pending_change
+        .close(ctx.accounts.payer.to_account_info())?;
+
+    Ok(0)
+}
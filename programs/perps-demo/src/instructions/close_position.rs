@@ -1,9 +1,20 @@
 //! ClosePosition instruction handler
 
 use {
-    crate::state::{custody::Custody, perpetuals::Perpetuals, pool::Pool, position::Position},
-    anchor_lang::prelude::*,
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
     anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
 };
 
 #[derive(Accounts)]
@@ -13,7 +24,7 @@ pub struct ClosePosition<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == custody.mint,
+        constraint = receiving_account.mint == collateral_custody.mint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
@@ -42,12 +53,13 @@ pub struct ClosePosition<'info> {
     #[account(
         mut,
         has_one = owner,
+        has_one = collateral_custody,
         seeds = [b"position",
                  owner.key().as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
                  &[position.side as u8]],
-        bump
+        bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
 
@@ -60,20 +72,44 @@ pub struct ClosePosition<'info> {
     )]
     pub custody: Box<Account<'info, Custody>>,
 
-    /// CHECK: oracle account for the collateral token
+    /// CHECK: oracle account for the position token
     #[account(
         constraint = custody_oracle_account.key() == custody.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
     )]
-    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
 
     token_program: Program<'info, Token>,
 }
@@ -81,24 +117,120 @@ pub struct ClosePosition<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct ClosePositionParams {
     price: u64,
+    // position size to close, the whole position is closed if zero
     size_usd: u64,
-    collateral_only: u64,
-    size_only: u64,
-    profit_only: u64,
+    deadline: Option<i64>,
 }
 
-pub fn close_position(_ctx: Context<ClosePosition>, _params: &ClosePositionParams) -> Result<()> {
+pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams) -> Result<()> {
     // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::ClosePosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+    if params.price == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(position.size_usd > 0, PerpetualsError::InvalidPositionState);
 
     // compute exit price
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let exit_price = pool.get_position_price(&token_price)?;
+    msg!("Exit price: {}", exit_price);
+
+    if position.side == Side::Long {
+        require_gte!(exit_price, params.price, PerpetualsError::MaxPriceSlippage);
+    } else {
+        require_gte!(params.price, exit_price, PerpetualsError::MaxPriceSlippage);
+    }
 
     // compute amount to close
-
-    // check collateral balance
-
-    // unlock pool funds
+    let size_usd = if params.size_usd == 0 || params.size_usd > position.size_usd {
+        position.size_usd
+    } else {
+        params.size_usd
+    };
+    let size_usd = pool.get_close_size(position, custody, size_usd)?;
+    let transfer_amount = pool.decrease_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        size_usd,
+        0,
+    )?;
+    let fee_amount = std::cmp::min(
+        pool.get_fee_amount(
+            custody.fees.close_position,
+            size_usd,
+            &collateral_token_price,
+            collateral_custody,
+        )?,
+        transfer_amount,
+    );
+    msg!("Fee amount: {}", fee_amount);
+    let insurance_amount = pool.collect_fee(fee_amount, custody, collateral_custody)?;
 
     // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(transfer_amount, fee_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        insurance_amount,
+    )?;
+
+    // update position
+    if position.size_usd == 0 {
+        msg!("Close position");
+        **position = Position::default();
+        ctx.accounts
+            .position
+            .close(ctx.accounts.owner.to_account_info())?;
+    } else {
+        position.time = curtime;
+    }
 
     Ok(())
 }
@@ -0,0 +1,172 @@
+//! CreateOrder instruction handler
+
+use {
+    crate::{
+        math,
+        state::{
+            custody::Custody,
+            order::Order,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::Side,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: CreateOrderParams)]
+pub struct CreateOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[params.side as u8],
+                 &params.order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = collateral_custody_mint,
+        token::authority = transfer_authority,
+        seeds = [b"order_token_account",
+                 order.key().as_ref()],
+        bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        constraint = collateral_custody_mint.key() == collateral_custody.mint
+    )]
+    pub collateral_custody_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateOrderParams {
+    order_id: u64,
+    trigger_price: u64,
+    collateral: u64,
+    size: u64,
+    side: Side,
+    execution_fee: u64,
+    expiration_time: i64,
+    deadline: Option<i64>,
+}
+
+pub fn create_order(ctx: Context<CreateOrder>, params: &CreateOrderParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::OpenPosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let curtime = perpetuals.get_time()?;
+    if params.trigger_price == 0
+        || params.collateral == 0
+        || params.size == 0
+        || params.side == Side::None
+        || params.expiration_time <= curtime
+    {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let pool = ctx.accounts.pool.as_mut();
+    pool.check_collateral_custody(
+        params.side,
+        &ctx.accounts.custody,
+        &ctx.accounts.collateral_custody,
+    )?;
+
+    // record order data
+    let order = ctx.accounts.order.as_mut();
+    order.owner = ctx.accounts.owner.key();
+    order.pool = pool.key();
+    order.custody = ctx.accounts.custody.key();
+    order.collateral_custody = ctx.accounts.collateral_custody.key();
+    order.order_id = params.order_id;
+    order.time = curtime;
+    order.expiration_time = params.expiration_time;
+    order.side = params.side;
+    order.trigger_price = params.trigger_price;
+    order.collateral = params.collateral;
+    order.execution_fee = params.execution_fee;
+    order.size = params.size;
+    order.bump = *ctx.bumps.get("order").ok_or(ProgramError::InvalidSeeds)?;
+    order.token_account_bump = *ctx
+        .bumps
+        .get("order_token_account")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    // escrow collateral and execution fee
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_add(params.collateral, params.execution_fee)?,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,284 @@
+//! ExecuteOrder instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            order::Order,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct ExecuteOrder<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = fee_receiving_account.mint == collateral_custody.mint,
+        constraint = fee_receiving_account.owner == keeper.key()
+    )]
+    pub fee_receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: order owner, receives the escrow account rent
+    #[account(
+        mut,
+        constraint = owner.key() == order.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    // order rent covers the position account rent paid by the keeper
+    #[account(
+        mut,
+        has_one = collateral_custody,
+        seeds = [b"order",
+                 order.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[order.side as u8],
+                 &order.order_id.to_le_bytes()],
+        bump = order.bump,
+        close = keeper
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [b"order_token_account",
+                 order.key().as_ref()],
+        bump = order.token_account_bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = Position::LEN,
+        seeds = [b"position",
+                 order.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[order.side as u8]],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ExecuteOrderParams {}
+
+pub fn execute_order(ctx: Context<ExecuteOrder>, _params: &ExecuteOrderParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    ctx.accounts.perpetuals.check_permission(
+        Permission::OpenPosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let order = ctx.accounts.order.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+
+    // check order state
+    msg!("Check order state");
+    let curtime = perpetuals.get_time()?;
+    require_gte!(
+        order.expiration_time,
+        curtime,
+        PerpetualsError::OrderExpired
+    );
+
+    // compute position price
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let position_price = pool.get_position_price(&token_price)?;
+    msg!("Position price: {}", position_price);
+
+    if order.side == Side::Long {
+        require_gte!(
+            order.trigger_price,
+            position_price,
+            PerpetualsError::OrderNotTriggered
+        );
+    } else {
+        require_gte!(
+            position_price,
+            order.trigger_price,
+            PerpetualsError::OrderNotTriggered
+        );
+    }
+
+    if position.size_usd == 0 {
+        // init a new position
+        msg!("Init new position");
+        position.owner = order.owner;
+        position.pool = pool.key();
+        position.custody = custody.key();
+        position.collateral_custody = collateral_custody.key();
+        position.side = order.side;
+        position.version = Position::VERSION;
+        position.bump = *ctx
+            .bumps
+            .get("position")
+            .ok_or(ProgramError::InvalidSeeds)?;
+    } else {
+        // position exists, check collateral custody and increase it
+        msg!("Increase existing position");
+        require_keys_eq!(
+            position.collateral_custody,
+            collateral_custody.key(),
+            PerpetualsError::InvalidCollateralCustody
+        );
+    }
+    position.time = curtime;
+
+    // update position, check position risk and lock funds for potential profit payoff
+    let insurance_amount = pool.increase_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        order.collateral,
+        order.size,
+    )?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(order.collateral, insurance_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        insurance_amount,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.fee_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        order.execution_fee,
+    )?;
+
+    perpetuals.close_token_account(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! ExecuteTrigger instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct ExecuteTrigger<'info> {
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = fee_receiving_account.mint == collateral_custody.mint,
+        constraint = fee_receiving_account.owner == executor.key()
+    )]
+    pub fee_receiving_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.owner == position.owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: position owner, receives the position account rent
+    #[account(
+        mut,
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = collateral_custody,
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ExecuteTriggerParams {}
+
+pub fn execute_trigger(ctx: Context<ExecuteTrigger>, _params: &ExecuteTriggerParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    ctx.accounts.perpetuals.check_permission(
+        Permission::ClosePosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(position.size_usd > 0, PerpetualsError::InvalidPositionState);
+
+    // compute exit price
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let exit_price = pool.get_position_price(&token_price)?;
+    msg!("Exit price: {}", exit_price);
+
+    // check if position can be closed
+    require!(
+        position.is_triggered(exit_price),
+        PerpetualsError::PositionNotTriggered
+    );
+
+    // compute amount to close
+    let size_usd =
+        if position.trigger_size_usd == 0 || position.trigger_size_usd > position.size_usd {
+            position.size_usd
+        } else {
+            position.trigger_size_usd
+        };
+    let size_usd = pool.get_close_size(position, custody, size_usd)?;
+    let transfer_amount = pool.decrease_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        size_usd,
+        0,
+    )?;
+    let fee_amount = std::cmp::min(
+        pool.get_fee_amount(
+            custody.fees.close_position,
+            size_usd,
+            &collateral_token_price,
+            collateral_custody,
+        )?,
+        transfer_amount,
+    );
+    msg!("Fee amount: {}", fee_amount);
+    let insurance_amount = pool.collect_fee(fee_amount, custody, collateral_custody)?;
+    let transfer_amount = math::checked_sub(transfer_amount, fee_amount)?;
+
+    let execution_fee_amount = std::cmp::min(
+        collateral_token_price
+            .get_token_amount(position.trigger_fee_usd, collateral_custody.decimals)?,
+        transfer_amount,
+    );
+    msg!("Execution fee: {}", execution_fee_amount);
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(transfer_amount, execution_fee_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.fee_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        execution_fee_amount,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        insurance_amount,
+    )?;
+
+    // update position
+    if position.size_usd == 0 {
+        msg!("Close position");
+        **position = Position::default();
+        ctx.accounts
+            .position
+            .close(ctx.accounts.owner.to_account_info())?;
+    } else {
+        // triggers fire once, remaining size stays open without them
+        position.time = curtime;
+        position.clear_triggers();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! GetAdlQueue instruction handler
+
+use {
+    crate::state::{
+        custody::Custody,
+        oracle::OraclePrice,
+        perpetuals::Perpetuals,
+        pool::{AdlQueue, Pool},
+        position::Side,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct GetAdlQueue<'info> {
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+    // remaining accounts:
+    //   candidate positions of the custody side (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetAdlQueueParams {
+    side: Side,
+}
+
+pub fn get_adl_queue(ctx: Context<GetAdlQueue>, params: &GetAdlQueueParams) -> Result<AdlQueue> {
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        ctx.accounts.custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        ctx.accounts.custody.oracle.max_price_error,
+        ctx.accounts.custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        ctx.accounts.collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        ctx.accounts.collateral_custody.oracle.max_price_error,
+        ctx.accounts.collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    ctx.accounts.pool.get_adl_queue(
+        ctx.remaining_accounts,
+        params.side,
+        &ctx.accounts.custody,
+        &ctx.accounts.collateral_custody,
+        &token_price,
+        &collateral_token_price,
+    )
+}
@@ -0,0 +1,32 @@
+//! GetAuditLog instruction handler
+
+use {
+    crate::state::audit_log::{AuditLog, AuditLogPage},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct GetAuditLog<'info> {
+    #[account(
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetAuditLogParams {
+    // sequence number of the first entry, entries that were overwritten are skipped
+    start: u64,
+    count: u8,
+}
+
+pub fn get_audit_log(
+    ctx: Context<GetAuditLog>,
+    params: &GetAuditLogParams,
+) -> Result<AuditLogPage> {
+    Ok(ctx
+        .accounts
+        .audit_log
+        .get_page(params.start, params.count as usize))
+}
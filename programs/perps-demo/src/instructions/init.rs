@@ -1,7 +1,13 @@
 //! Init instruction handler
 
 use {
-    crate::state::perpetuals::Perpetuals, anchor_lang::prelude::*, anchor_spl::token::Token,
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::Multisig,
+        perpetuals::{Permissions, Perpetuals},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::Token,
     solana_program::program_error::ProgramError,
 };
 
@@ -29,16 +35,46 @@ pub struct Init<'info> {
     )]
     pub perpetuals: Box<Account<'info, Perpetuals>>,
 
+    #[account(
+        init,
+        payer = admin,
+        space = Multisig::LEN,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = AuditLog::LEN,
+        seeds = [b"audit_log"],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct InitParams {}
+pub struct InitParams {
+    pub min_signatures: u8,
+}
+
+pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
+    // record admin signers, passed as remaining accounts
+    let multisig = ctx.accounts.multisig.as_mut();
+    multisig.set_signers(ctx.remaining_accounts, params.min_signatures)?;
+    multisig.bump = *ctx
+        .bumps
+        .get("multisig")
+        .ok_or(ProgramError::InvalidSeeds)?;
 
-pub fn init(ctx: Context<Init>, _params: &InitParams) -> Result<()> {
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     perpetuals.admin = ctx.accounts.admin.key();
+    perpetuals.permissions = Permissions::default();
+    perpetuals.version = Perpetuals::VERSION;
     perpetuals.transfer_authority_bump = *ctx
         .bumps
         .get("transfer_authority")
@@ -48,5 +84,13 @@ pub fn init(ctx: Context<Init>, _params: &InitParams) -> Result<()> {
         .get("perpetuals")
         .ok_or(ProgramError::InvalidSeeds)?;
 
+    // record audit log entry
+    let audit_log = ctx.accounts.audit_log.as_mut();
+    audit_log.bump = *ctx
+        .bumps
+        .get("audit_log")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    audit_log.record(AuditAction::Init, ctx.accounts.admin.key(), params)?;
+
     Ok(())
 }
@@ -1,7 +1,17 @@
 //! Liquidate instruction handler
 
 use {
-    crate::state::{custody::Custody, perpetuals::Perpetuals, pool::Pool, position::Position},
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::Position,
+        },
+    },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
 };
@@ -13,14 +23,14 @@ pub struct Liquidate<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == custody.mint,
+        constraint = receiving_account.mint == collateral_custody.mint,
         constraint = receiving_account.owner == position.owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = reward_receiving_account.mint == custody.mint,
+        constraint = reward_receiving_account.mint == collateral_custody.mint,
         constraint = reward_receiving_account.owner == signer.key()
     )]
     pub reward_receiving_account: Box<Account<'info, TokenAccount>>,
@@ -48,12 +58,14 @@ pub struct Liquidate<'info> {
 
     #[account(
         mut,
+        has_one = collateral_custody,
         seeds = [b"position",
                  position.owner.as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
                  &[position.side as u8]],
-        bump
+        bump = position.bump,
+        close = signer
     )]
     pub position: Box<Account<'info, Position>>,
 
@@ -66,20 +78,44 @@ pub struct Liquidate<'info> {
     )]
     pub custody: Box<Account<'info, Custody>>,
 
-    /// CHECK: oracle account for the collateral token
+    /// CHECK: oracle account for the position token
     #[account(
         constraint = custody_oracle_account.key() == custody.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_bump
     )]
-    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
 
     token_program: Program<'info, Token>,
 }
@@ -87,20 +123,137 @@ pub struct Liquidate<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct LiquidateParams {}
 
-pub fn liquidate(_ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<()> {
+#[event]
+pub struct BadDebtEvent {
+    pub position: Pubkey,
+    pub custody: Pubkey,
+    // loss not covered by the position margin, in collateral custody tokens
+    pub bad_debt_amount: u64,
+}
+
+pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    ctx.accounts.perpetuals.check_permission(
+        Permission::Liquidation,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+
     // check if position can be liquidated
+    msg!("Check position state");
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
 
-    // compute exit price
+    require!(
+        !pool.check_leverage(position, &token_price, custody, false)?,
+        PerpetualsError::InvalidPositionState
+    );
 
-    // compute amount to close
+    // compute loss not covered by the position margin
+    let (profit_usd, loss_usd) = pool.get_pnl_usd(position, &token_price)?;
+    let margin_usd = math::checked_add(position.collateral_usd, profit_usd)?;
+    let shortfall_usd = if loss_usd > margin_usd {
+        math::checked_sub(loss_usd, margin_usd)?
+    } else {
+        0
+    };
+    msg!("Shortfall USD: {}", shortfall_usd);
 
-    // check collateral balance
+    // close position and unlock pool funds
+    let size_usd = position.size_usd;
+    let transfer_amount = pool.decrease_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        size_usd,
+        0,
+    )?;
 
-    // update position
+    // liquidation fee is paid out of the remaining margin
+    let fee_amount = std::cmp::min(
+        pool.get_fee_amount(
+            custody.fees.liquidation,
+            size_usd,
+            &collateral_token_price,
+            collateral_custody,
+        )?,
+        transfer_amount,
+    );
+    let insurance_amount = pool.get_insurance_amount(fee_amount, custody)?;
+    msg!("Liquidation fee: {}", fee_amount);
 
-    // unlock pool funds
+    // the shortfall is profit LPs forgo, no tokens leave the pool for it and
+    // owned funds already hold the whole position collateral, so it is only recorded
+    let bad_debt_amount =
+        collateral_token_price.get_token_amount(shortfall_usd, collateral_custody.decimals)?;
+    msg!("Bad debt: {}", bad_debt_amount);
+    collateral_custody.bad_debt = math::checked_add(collateral_custody.bad_debt, bad_debt_amount)?;
+
+    if bad_debt_amount > 0 {
+        emit!(BadDebtEvent {
+            position: position.key(),
+            custody: collateral_custody.key(),
+            bad_debt_amount,
+        });
+    }
 
     // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(transfer_amount, fee_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.reward_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(fee_amount, insurance_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        insurance_amount,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,98 @@
+//! MigratePool instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// CHECK: legacy pool account, deserialized before it is reallocated
+    #[account(mut)]
+    pub pool: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MigratePoolParams {}
+
+pub fn migrate_pool(ctx: Context<MigratePool>, params: &MigratePoolParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::MigratePool, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // load and validate legacy account
+    msg!("Load legacy account");
+    let account = ctx.accounts.pool.to_account_info();
+    let mut pool: Pool = Perpetuals::load_legacy_account(account.clone())?;
+    require!(
+        pool.version < Pool::VERSION,
+        PerpetualsError::AccountAlreadyMigrated
+    );
+
+    // reallocate account
+    msg!("Reallocate account");
+    Perpetuals::grow_legacy_account(
+        account.clone(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // upgrade account
+    msg!("Upgrade account to version {}", Pool::VERSION);
+    pool.version = Pool::VERSION;
+    pool.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::MigratePool,
+        ctx.accounts.admin.key(),
+        &account.key(),
+    )?;
+
+    Ok(0)
+}
@@ -1,14 +1,20 @@
 //! OpenPosition instruction handler
 
 use {
-    crate::state::{
-        custody::Custody,
-        perpetuals::Perpetuals,
-        pool::Pool,
-        position::{Position, Side},
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
 };
 
 #[derive(Accounts)]
@@ -19,7 +25,7 @@ pub struct OpenPosition<'info> {
 
     #[account(
         mut,
-        constraint = funding_account.mint == custody.mint,
+        constraint = funding_account.mint == collateral_custody.mint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
@@ -67,20 +73,44 @@ pub struct OpenPosition<'info> {
     )]
     pub custody: Box<Account<'info, Custody>>,
 
-    /// CHECK: oracle account for the collateral token
+    /// CHECK: oracle account for the position token
     #[account(
         constraint = custody_oracle_account.key() == custody.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
     )]
-    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
 
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
@@ -92,31 +122,122 @@ pub struct OpenPositionParams {
     collateral: u64,
     size: u64,
     side: Side,
+    deadline: Option<i64>,
 }
 
 pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) -> Result<()> {
     // validate inputs
-
-    // compute position price
-
-    // compute amount to transfer
-
-    // if position exists, check the owner and update
-
-    // otherwise init a new position
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::OpenPosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+    if params.price == 0 || params.collateral == 0 || params.size == 0 || params.side == Side::None
+    {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
     let position = ctx.accounts.position.as_mut();
-    position.owner = ctx.accounts.owner.key();
-    position.side = params.side;
-    position.bump = *ctx
-        .bumps
-        .get("position")
-        .ok_or(ProgramError::InvalidSeeds)?;
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
 
-    // check position risk
+    pool.check_collateral_custody(params.side, custody, collateral_custody)?;
 
-    // lock funds for potential profit payoff
+    // compute position price
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let position_price = pool.get_position_price(&token_price)?;
+    msg!("Position price: {}", position_price);
+
+    if params.side == Side::Long {
+        require_gte!(
+            params.price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            params.price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    if position.size_usd == 0 {
+        // init a new position
+        msg!("Init new position");
+        position.owner = ctx.accounts.owner.key();
+        position.pool = pool.key();
+        position.custody = custody.key();
+        position.collateral_custody = collateral_custody.key();
+        position.side = params.side;
+        position.version = Position::VERSION;
+        position.bump = *ctx
+            .bumps
+            .get("position")
+            .ok_or(ProgramError::InvalidSeeds)?;
+    } else {
+        // position exists, check collateral custody and increase it
+        msg!("Increase existing position");
+        require_keys_eq!(
+            position.collateral_custody,
+            collateral_custody.key(),
+            PerpetualsError::InvalidCollateralCustody
+        );
+    }
+    position.time = curtime;
+
+    // update position, check position risk and lock funds for potential profit payoff
+    let insurance_amount = pool.increase_position(
+        position,
+        custody,
+        collateral_custody,
+        &token_price,
+        &collateral_token_price,
+        params.collateral,
+        params.size,
+    )?;
 
     // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_sub(params.collateral, insurance_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        insurance_amount,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,87 @@
+//! ProposeAdmin instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProposeAdminParams {
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminProposedEvent {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+pub fn propose_admin(ctx: Context<ProposeAdmin>, params: &ProposeAdminParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::ProposeAdmin, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    if params.new_admin == Pubkey::default() {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    perpetuals.pending_admin = params.new_admin;
+
+    emit!(AdminProposedEvent {
+        admin: perpetuals.admin,
+        pending_admin: perpetuals.pending_admin,
+    });
+
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::ProposeAdmin, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
+}
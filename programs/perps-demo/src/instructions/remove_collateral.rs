@@ -0,0 +1,218 @@
+//! RemoveCollateral instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: RemoveCollateralParams)]
+pub struct RemoveCollateral<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = collateral_custody,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveCollateralParams {
+    collateral_usd: u64,
+    deadline: Option<i64>,
+}
+
+pub fn remove_collateral(
+    ctx: Context<RemoveCollateral>,
+    params: &RemoveCollateralParams,
+) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::ClosePosition,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody, &ctx.accounts.collateral_custody],
+    )?;
+    let position = ctx.accounts.position.as_mut();
+    if params.collateral_usd == 0 || params.collateral_usd >= position.collateral_usd {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+
+    // compute amount to transfer
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        custody.oracle.oracle_type,
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        custody.oracle.max_price_error,
+        custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        collateral_custody.oracle.oracle_type,
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        collateral_custody.oracle.max_price_error,
+        collateral_custody.oracle.max_price_age_sec,
+        curtime,
+    )?;
+
+    let transfer_amount = collateral_token_price
+        .get_token_amount(params.collateral_usd, collateral_custody.decimals)?;
+    let collateral_amount = math::checked_as_u64(math::checked_div(
+        math::checked_mul(
+            position.collateral_amount as u128,
+            params.collateral_usd as u128,
+        )?,
+        position.collateral_usd as u128,
+    )?)?;
+    msg!("Amount out: {}", transfer_amount);
+
+    // update position
+    msg!("Update position");
+    position.time = curtime;
+    position.collateral_usd = math::checked_sub(position.collateral_usd, params.collateral_usd)?;
+    position.collateral_amount = math::checked_sub(position.collateral_amount, collateral_amount)?;
+
+    // check position risk
+    msg!("Check position risks");
+    require!(
+        pool.check_leverage(position, &token_price, custody, false)?,
+        PerpetualsError::MaxLeverage
+    );
+    pool.check_position_size(position, custody)?;
+
+    // profit paid out above the position collateral comes from unlocked funds only
+    if transfer_amount > collateral_amount {
+        require_gte!(
+            pool.get_available_amount(collateral_custody),
+            math::checked_sub(transfer_amount, collateral_amount)?,
+            PerpetualsError::InsufficientLiquidity
+        );
+    }
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    collateral_custody.assets.collateral =
+        math::checked_sub(collateral_custody.assets.collateral, collateral_amount)?;
+    if transfer_amount > collateral_amount {
+        collateral_custody.assets.owned = math::checked_sub(
+            collateral_custody.assets.owned,
+            math::checked_sub(transfer_amount, collateral_amount)?,
+        )?;
+    } else {
+        collateral_custody.assets.owned = math::checked_add(
+            collateral_custody.assets.owned,
+            math::checked_sub(collateral_amount, transfer_amount)?,
+        )?;
+    }
+
+    Ok(())
+}
@@ -2,8 +2,14 @@
 
 use {
     crate::{
+        error::PerpetualsError,
         math,
-        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Mint, Token, TokenAccount},
@@ -92,6 +98,9 @@ pub struct RemoveLiquidity<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct RemoveLiquidityParams {
     lp_amount: u64,
+    // if set, removes up to the available amount and burns lp tokens pro rata
+    allow_partial: bool,
+    deadline: Option<i64>,
 }
 
 pub fn remove_liquidity(
@@ -100,6 +109,12 @@ pub fn remove_liquidity(
 ) -> Result<()> {
     // validate inputs
     msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::RemoveLiquidity,
+        &ctx.accounts.pool,
+        &[&ctx.accounts.custody],
+    )?;
     if params.lp_amount == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
@@ -126,7 +141,22 @@ pub fn remove_liquidity(
         math::checked_mul(pool_amount_usd, params.lp_amount as u128)?,
         ctx.accounts.lp_token_mint.supply as u128,
     )?)?;
-    let remove_amount = token_price.get_token_amount(remove_amount_usd, custody.decimals)?;
+    let mut remove_amount = token_price.get_token_amount(remove_amount_usd, custody.decimals)?;
+    let mut lp_amount = params.lp_amount;
+
+    // only unlocked funds can be withdrawn
+    let available_amount = pool.get_available_amount(custody);
+    if remove_amount > available_amount {
+        require!(params.allow_partial, PerpetualsError::InsufficientLiquidity);
+        // round lp tokens to burn up so partial removals can't be used to
+        // withdraw slightly more than the burned share
+        lp_amount = math::checked_as_u64(math::checked_ceil_div(
+            math::checked_mul(lp_amount as u128, available_amount as u128)?,
+            remove_amount as u128,
+        )?)?;
+        remove_amount = available_amount;
+        require!(lp_amount > 0, PerpetualsError::InsufficientLiquidity);
+    }
     msg!("Amount removed: {}", remove_amount);
 
     // transfer tokens
@@ -146,7 +176,7 @@ pub fn remove_liquidity(
         ctx.accounts.lp_token_account.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
-        params.lp_amount,
+        lp_amount,
     )?;
 
     // update custody stats
@@ -0,0 +1,116 @@
+//! RemovePool instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+    anchor_spl::token::Mint,
+};
+
+#[derive(Accounts)]
+pub struct RemovePool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    // spl token mints can't be closed, the empty mint is reused if the pool is added again
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemovePoolParams {}
+
+pub fn remove_pool(ctx: Context<RemovePool>, params: &RemovePoolParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::RemovePool, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    msg!("Validate inputs");
+    require!(
+        ctx.accounts.pool.tokens.is_empty() && ctx.accounts.lp_token_mint.supply == 0,
+        PerpetualsError::PoolNotEmpty
+    );
+
+    // update perpetuals data
+    msg!("Update perpetuals data");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let pool_key = ctx.accounts.pool.key();
+    let pool_id = perpetuals
+        .pools
+        .iter()
+        .position(|&k| k == pool_key)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    perpetuals.pools.remove(pool_id);
+    Perpetuals::shrink_account(
+        perpetuals.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        Perpetuals::LEN + perpetuals.pools.len() * std::mem::size_of::<Pubkey>(),
+    )?;
+
+    // close pool account
+    msg!("Close pool account");
+    **ctx.accounts.pool = Pool::default();
+    ctx.accounts
+        .pool
+        .close(ctx.accounts.admin.to_account_info())?;
+
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::RemovePool, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
+}
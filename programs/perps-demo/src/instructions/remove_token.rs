@@ -0,0 +1,159 @@
+//! RemoveToken instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::{Pool, PoolToken},
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct RemoveToken<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.insurance_fund_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveTokenParams {}
+
+pub fn remove_token(ctx: Context<RemoveToken>, params: &RemoveTokenParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::RemoveToken, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    msg!("Validate inputs");
+    let custody = ctx.accounts.custody.as_ref();
+    require!(
+        custody.assets.owned == 0
+            && custody.assets.locked == 0
+            && custody.assets.collateral == 0
+            && custody.open_interest.long_usd == 0
+            && custody.open_interest.short_usd == 0
+            && ctx.accounts.insurance_fund_token_account.amount == 0,
+        PerpetualsError::CustodyNotEmpty
+    );
+
+    // update pool data
+    msg!("Update pool data");
+    let pool = ctx.accounts.pool.as_mut();
+    let token_id = pool.get_token_id(&custody.key())?;
+    pool.tokens.remove(token_id);
+    Perpetuals::shrink_account(
+        pool.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        Pool::LEN + pool.tokens.len() * std::mem::size_of::<PoolToken>(),
+    )?;
+
+    // close custody accounts
+    msg!("Close custody accounts");
+    ctx.accounts.perpetuals.close_token_account(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
+
+    ctx.accounts.perpetuals.close_token_account(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.insurance_fund_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
+
+    **ctx.accounts.custody = Custody::default();
+    ctx.accounts
+        .custody
+        .close(ctx.accounts.admin.to_account_info())?;
+
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::RemoveToken, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
+}
@@ -0,0 +1,70 @@
+//! SetAdminSigners instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetAdminSigners<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetAdminSignersParams {
+    pub min_signatures: u8,
+}
+
+pub fn set_admin_signers(
+    ctx: Context<SetAdminSigners>,
+    params: &SetAdminSignersParams,
+) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::SetAdminSigners, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // replace signers, new ones are passed as remaining accounts
+    ctx.accounts
+        .multisig
+        .set_signers(ctx.remaining_accounts, params.min_signatures)?;
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::SetAdminSigners,
+        ctx.accounts.admin.key(),
+        params,
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,160 @@
+//! SetCustodyConfig instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            custody::{Custody, CustodyConfig, Fees, OracleParams, PricingParams},
+            multisig::{AdminInstruction, Multisig},
+            pending_change::{ChangeType, PendingChange},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct SetCustodyConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    // queuing a new change replaces the pending one and restarts the delay
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = PendingChange::LEN,
+        seeds = [b"pending_change",
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[ChangeType::CustodyConfig as u8]],
+        bump
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCustodyConfigParams {
+    pub is_stable: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub fees: Fees,
+}
+
+#[event]
+pub struct CustodyConfigQueuedEvent {
+    pub custody: Pubkey,
+    pub old_config: CustodyConfig,
+    pub new_config: CustodyConfig,
+    pub execute_time: i64,
+}
+
+pub fn set_custody_config(
+    ctx: Context<SetCustodyConfig>,
+    params: &SetCustodyConfigParams,
+) -> Result<u8> {
+    // rent is paid by the signer that initializes the account, which may not be the last one
+    if ctx.accounts.pending_change.payer == Pubkey::default() {
+        ctx.accounts.pending_change.payer = ctx.accounts.admin.key();
+    }
+
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::SetCustodyConfig, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    msg!("Validate inputs");
+    let config = CustodyConfig {
+        is_stable: params.is_stable,
+        oracle: params.oracle,
+        pricing: params.pricing,
+        fees: params.fees,
+    };
+    if !config.validate() {
+        return err!(PerpetualsError::InvalidTokenConfig);
+    }
+
+    // queue the change, it is applied with apply_pending_change once the delay has passed
+    msg!("Queue custody config change");
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let pending_change = ctx.accounts.pending_change.as_mut();
+    pending_change.pool = ctx.accounts.pool.key();
+    pending_change.custody = ctx.accounts.custody.key();
+    pending_change.change_type = ChangeType::CustodyConfig;
+    pending_change.config = config;
+    pending_change.execute_time =
+        math::checked_add(perpetuals.get_time()?, perpetuals.get_timelock_delay()?)?;
+    pending_change.bump = *ctx
+        .bumps
+        .get("pending_change")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    emit!(CustodyConfigQueuedEvent {
+        custody: pending_change.custody,
+        old_config: ctx.accounts.custody.get_config(),
+        new_config: config,
+        execute_time: pending_change.execute_time,
+    });
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::SetCustodyConfig,
+        ctx.accounts.admin.key(),
+        params,
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,123 @@
+//! SetDepositCaps instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            custody::Custody,
+            pending_change::{ChangeType, DepositCaps, PendingChange},
+            perpetuals::{Perpetuals, Role},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct SetDepositCaps<'info> {
+    #[account(mut)]
+    pub parameter_manager: Signer<'info>,
+
+    #[account(
+        constraint = perpetuals.has_role(parameter_manager.key, Role::ParameterManager) @ PerpetualsError::MissingRole,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    // queuing a new change replaces the pending one and restarts the delay
+    #[account(
+        init_if_needed,
+        payer = parameter_manager,
+        space = PendingChange::LEN,
+        seeds = [b"pending_change",
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[ChangeType::DepositCaps as u8]],
+        bump
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetDepositCapsParams {
+    pub max_aum_usd: u128,
+    pub max_amount: u64,
+}
+
+#[event]
+pub struct DepositCapsQueuedEvent {
+    pub custody: Pubkey,
+    pub old_caps: DepositCaps,
+    pub new_caps: DepositCaps,
+    pub execute_time: i64,
+}
+
+pub fn set_deposit_caps(ctx: Context<SetDepositCaps>, params: &SetDepositCapsParams) -> Result<()> {
+    // queue the change, it is applied with apply_pending_change once the delay has passed
+    msg!("Queue deposit caps change");
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let pending_change = ctx.accounts.pending_change.as_mut();
+    if pending_change.payer == Pubkey::default() {
+        pending_change.payer = ctx.accounts.parameter_manager.key();
+    }
+    pending_change.pool = ctx.accounts.pool.key();
+    pending_change.custody = ctx.accounts.custody.key();
+    pending_change.change_type = ChangeType::DepositCaps;
+    pending_change.deposit_caps = DepositCaps {
+        max_aum_usd: params.max_aum_usd,
+        max_amount: params.max_amount,
+    };
+    pending_change.execute_time =
+        math::checked_add(perpetuals.get_time()?, perpetuals.get_timelock_delay()?)?;
+    pending_change.bump = *ctx
+        .bumps
+        .get("pending_change")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    emit!(DepositCapsQueuedEvent {
+        custody: pending_change.custody,
+        old_caps: DepositCaps {
+            max_aum_usd: ctx.accounts.pool.max_aum_usd,
+            max_amount: ctx.accounts.custody.max_amount,
+        },
+        new_caps: pending_change.deposit_caps,
+        execute_time: pending_change.execute_time,
+    });
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::SetDepositCaps,
+        ctx.accounts.parameter_manager.key(),
+        params,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! SetPermissions instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        custody::Custody,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::{Permissions, Perpetuals, Role},
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetPermissions<'info> {
+    // multisig signer, or the pauser if permissions are only revoked
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    // optional pool or custody account to update is passed as a remaining account,
+    // global permissions are updated if it is omitted
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPermissionsParams {
+    pub permissions: Permissions,
+}
+
+#[event]
+pub struct PermissionsChangedEvent {
+    // perpetuals, pool or custody account
+    pub account: Pubkey,
+    pub old_permissions: Permissions,
+    pub new_permissions: Permissions,
+}
+
+pub fn set_permissions(ctx: Context<SetPermissions>, params: &SetPermissionsParams) -> Result<u8> {
+    // the pauser can revoke permissions on its own, anything else needs the multisig
+    let old_permissions = match ctx.remaining_accounts.first() {
+        None => ctx.accounts.perpetuals.permissions,
+        Some(target) => {
+            if let Ok(pool) = Account::<Pool>::try_from(target) {
+                pool.permissions
+            } else {
+                Account::<Custody>::try_from(target)?.permissions
+            }
+        }
+    };
+    if !old_permissions.contains(&params.permissions)
+        || !ctx
+            .accounts
+            .perpetuals
+            .has_role(ctx.accounts.admin.key, Role::Pauser)
+    {
+        // validate signatures
+        let instruction_accounts = Multisig::get_account_keys(&ctx);
+        let instruction_data =
+            Multisig::get_instruction_data(AdminInstruction::SetPermissions, params)?;
+        let signatures_left = ctx.accounts.multisig.sign_multisig(
+            &ctx.accounts.admin,
+            &instruction_accounts[1..],
+            &instruction_data,
+        )?;
+        if signatures_left > 0 {
+            msg!(
+                "Instruction has been signed but more signatures are required: {}",
+                signatures_left
+            );
+            return Ok(signatures_left);
+        }
+    }
+
+    let account = match ctx.remaining_accounts.first() {
+        None => {
+            msg!("Update global permissions");
+            let perpetuals = ctx.accounts.perpetuals.as_mut();
+            perpetuals.permissions = params.permissions;
+            perpetuals.key()
+        }
+        Some(target) => {
+            if let Ok(mut pool) = Account::<Pool>::try_from(target) {
+                msg!("Update pool permissions");
+                pool.permissions = params.permissions;
+                pool.exit(&crate::ID)?;
+                pool.key()
+            } else {
+                msg!("Update custody permissions");
+                let mut custody = Account::<Custody>::try_from(target)?;
+                custody.permissions = params.permissions;
+                custody.exit(&crate::ID)?;
+                custody.key()
+            }
+        }
+    };
+
+    emit!(PermissionsChangedEvent {
+        account,
+        old_permissions,
+        new_permissions: params.permissions,
+    });
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::SetPermissions,
+        ctx.accounts.admin.key(),
+        params,
+    )?;
+
+    Ok(0)
+}
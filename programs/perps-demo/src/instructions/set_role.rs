@@ -0,0 +1,86 @@
+//! SetRole instruction handler
+
+use {
+    crate::state::{
+        audit_log::{AuditAction, AuditLog},
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::{Perpetuals, Role},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetRoleParams {
+    pub role: Role,
+    // default pubkey revokes the role
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct RoleChangedEvent {
+    pub role: Role,
+    pub old_account: Pubkey,
+    pub new_account: Pubkey,
+}
+
+pub fn set_role(ctx: Context<SetRole>, params: &SetRoleParams) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data = Multisig::get_instruction_data(AdminInstruction::SetRole, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let old_account = perpetuals.roles.get(params.role);
+    perpetuals.roles.set(params.role, params.account);
+
+    emit!(RoleChangedEvent {
+        role: params.role,
+        old_account,
+        new_account: params.account,
+    });
+
+    // record audit log entry
+    ctx.accounts
+        .audit_log
+        .record(AuditAction::SetRole, ctx.accounts.admin.key(), params)?;
+
+    Ok(0)
+}
@@ -1,17 +1,25 @@
 //! SetTestOraclePrice instruction handler
 
 use {
-    crate::state::{custody::Custody, oracle::TestOracle, perpetuals::Perpetuals, pool::Pool},
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::TestOracle,
+            perpetuals::{Perpetuals, Role},
+            pool::Pool,
+        },
+    },
     anchor_lang::prelude::*,
 };
 
 #[derive(Accounts)]
 pub struct SetTestOraclePrice<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub oracle_updater: Signer<'info>,
 
     #[account(
-        has_one = admin,
+        constraint = perpetuals.has_role(oracle_updater.key, Role::OracleUpdater) @ PerpetualsError::MissingRole,
         seeds = [b"perpetuals"],
         bump = perpetuals.perpetuals_bump
     )]
@@ -34,7 +42,7 @@ pub struct SetTestOraclePrice<'info> {
 
     #[account(
         init_if_needed,
-        payer = admin,
+        payer = oracle_updater,
         space = TestOracle::LEN,
         constraint = oracle_account.key() == custody.oracle.oracle_account,
         seeds = [b"oracle_account",
@@ -59,6 +67,10 @@ pub fn set_test_oracle_price(
     ctx: Context<SetTestOraclePrice>,
     params: &SetTestOraclePriceParams,
 ) -> Result<()> {
+    // price updates are not admin config changes and are not recorded in the audit log,
+    // a feed pushing every few slots would evict the admin history from the ring buffer,
+    // oracle config changes go through set_custody_config and are recorded there
+
     // update oracle data
     let oracle_account = ctx.accounts.oracle_account.as_mut();
     oracle_account.price = params.price;
@@ -0,0 +1,107 @@
+//! SetTimelockDelay instruction handler
+
+use {
+    crate::{
+        math,
+        state::{
+            audit_log::{AuditAction, AuditLog},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"audit_log"],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetTimelockDelayParams {
+    pub timelock_delay_sec: i64,
+}
+
+pub fn set_timelock_delay(
+    ctx: Context<SetTimelockDelay>,
+    params: &SetTimelockDelayParams,
+) -> Result<u8> {
+    // validate signatures
+    let instruction_accounts = Multisig::get_account_keys(&ctx);
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::SetTimelockDelay, params)?;
+    let signatures_left = ctx.accounts.multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &instruction_accounts[1..],
+        &instruction_data,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    if params.timelock_delay_sec < 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // increases take effect immediately, reductions are queued behind the current delay
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let timelock_delay_sec = perpetuals.get_timelock_delay()?;
+    if params.timelock_delay_sec >= timelock_delay_sec {
+        msg!(
+            "Timelock delay: {} -> {}",
+            timelock_delay_sec,
+            params.timelock_delay_sec
+        );
+        perpetuals.timelock_delay_sec = params.timelock_delay_sec;
+        perpetuals.pending_timelock_delay_sec = 0;
+        perpetuals.pending_timelock_delay_time = 0;
+    } else {
+        let execute_time = math::checked_add(perpetuals.get_time()?, timelock_delay_sec)?;
+        msg!(
+            "Timelock delay: {} -> {} at {}",
+            timelock_delay_sec,
+            params.timelock_delay_sec,
+            execute_time
+        );
+        perpetuals.timelock_delay_sec = timelock_delay_sec;
+        perpetuals.pending_timelock_delay_sec = params.timelock_delay_sec;
+        perpetuals.pending_timelock_delay_time = execute_time;
+    }
+
+    // record audit log entry
+    ctx.accounts.audit_log.record(
+        AuditAction::SetTimelockDelay,
+        ctx.accounts.admin.key(),
+        params,
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,80 @@
+//! SetTrigger instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct SetTrigger<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 position.custody.as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetTriggerParams {
+    stop_loss_price: u64,
+    take_profit_price: u64,
+    size_usd: u64,
+    execution_fee_usd: u64,
+    deadline: Option<i64>,
+}
+
+pub fn set_trigger(ctx: Context<SetTrigger>, params: &SetTriggerParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    let position = ctx.accounts.position.as_mut();
+    require!(position.size_usd > 0, PerpetualsError::InvalidPositionState);
+    if params.stop_loss_price > 0 && params.take_profit_price > 0 {
+        let is_valid = if position.side == Side::Long {
+            params.stop_loss_price < params.take_profit_price
+        } else {
+            params.stop_loss_price > params.take_profit_price
+        };
+        if !is_valid {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+    }
+
+    // update position
+    position.stop_loss_price = params.stop_loss_price;
+    position.take_profit_price = params.take_profit_price;
+    position.trigger_size_usd = params.size_usd;
+    position.trigger_fee_usd = params.execution_fee_usd;
+
+    Ok(())
+}
@@ -4,7 +4,12 @@ use {
     crate::{
         error::PerpetualsError,
         math,
-        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permission, Perpetuals},
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
@@ -107,11 +112,21 @@ pub struct Swap<'info> {
 pub struct SwapParams {
     amount_in: u64,
     min_amount_out: u64,
+    deadline: Option<i64>,
 }
 
 pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     // validate inputs
     msg!("Validate inputs");
+    ctx.accounts.perpetuals.check_deadline(params.deadline)?;
+    ctx.accounts.perpetuals.check_permission(
+        Permission::Swap,
+        &ctx.accounts.pool,
+        &[
+            &ctx.accounts.receiving_custody,
+            &ctx.accounts.dispensing_custody,
+        ],
+    )?;
     if params.amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
@@ -160,6 +175,13 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         PerpetualsError::InsufficientAmountReturned
     );
 
+    // only unlocked funds can be dispensed
+    require_gte!(
+        pool.get_available_amount(dispensing_custody),
+        amount_out,
+        PerpetualsError::InsufficientLiquidity
+    );
+
     // transfer tokens
     msg!("Transfer tokens");
     perpetuals.transfer_tokens_from_user(
@@ -7,7 +7,11 @@ mod instructions;
 mod math;
 mod state;
 
-use {anchor_lang::prelude::*, instructions::*};
+use {
+    anchor_lang::prelude::*,
+    instructions::*,
+    state::{audit_log::AuditLogPage, pool::AdlQueue},
+};
 
 declare_id!("FAXYuthnTA4m7bSivEoxFeNUCMACD5RTxKN99WNUNjAg");
 
@@ -20,14 +24,122 @@ pub mod perps_demo {
         instructions::init(ctx, &params)
     }
 
-    pub fn add_pool(ctx: Context<AddPool>, params: AddPoolParams) -> Result<()> {
+    pub fn add_pool(ctx: Context<AddPool>, params: AddPoolParams) -> Result<u8> {
         instructions::add_pool(ctx, &params)
     }
 
-    pub fn add_token(ctx: Context<AddToken>, params: AddTokenParams) -> Result<()> {
+    pub fn add_token(ctx: Context<AddToken>, params: AddTokenParams) -> Result<u8> {
         instructions::add_token(ctx, &params)
     }
 
+    pub fn remove_pool(ctx: Context<RemovePool>, params: RemovePoolParams) -> Result<u8> {
+        instructions::remove_pool(ctx, &params)
+    }
+
+    pub fn remove_token(ctx: Context<RemoveToken>, params: RemoveTokenParams) -> Result<u8> {
+        instructions::remove_token(ctx, &params)
+    }
+
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, params: ProposeAdminParams) -> Result<u8> {
+        instructions::propose_admin(ctx, &params)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>, params: AcceptAdminParams) -> Result<()> {
+        instructions::accept_admin(ctx, &params)
+    }
+
+    pub fn cancel_admin_proposal(
+        ctx: Context<CancelAdminProposal>,
+        params: CancelAdminProposalParams,
+    ) -> Result<u8> {
+        instructions::cancel_admin_proposal(ctx, &params)
+    }
+
+    pub fn cancel_pending_change(
+        ctx: Context<CancelPendingChange>,
+        params: CancelPendingChangeParams,
+    ) -> Result<u8> {
+        instructions::cancel_pending_change(ctx, &params)
+    }
+
+    pub fn set_admin_signers(
+        ctx: Context<SetAdminSigners>,
+        params: SetAdminSignersParams,
+    ) -> Result<u8> {
+        instructions::set_admin_signers(ctx, &params)
+    }
+
+    pub fn set_custody_config(
+        ctx: Context<SetCustodyConfig>,
+        params: SetCustodyConfigParams,
+    ) -> Result<u8> {
+        instructions::set_custody_config(ctx, &params)
+    }
+
+    pub fn set_deposit_caps(
+        ctx: Context<SetDepositCaps>,
+        params: SetDepositCapsParams,
+    ) -> Result<()> {
+        instructions::set_deposit_caps(ctx, &params)
+    }
+
+    pub fn set_permissions(
+        ctx: Context<SetPermissions>,
+        params: SetPermissionsParams,
+    ) -> Result<u8> {
+        instructions::set_permissions(ctx, &params)
+    }
+
+    pub fn set_role(ctx: Context<SetRole>, params: SetRoleParams) -> Result<u8> {
+        instructions::set_role(ctx, &params)
+    }
+
+    pub fn set_timelock_delay(
+        ctx: Context<SetTimelockDelay>,
+        params: SetTimelockDelayParams,
+    ) -> Result<u8> {
+        instructions::set_timelock_delay(ctx, &params)
+    }
+
+    pub fn top_up_insurance_fund(
+        ctx: Context<TopUpInsuranceFund>,
+        params: TopUpInsuranceFundParams,
+    ) -> Result<u8> {
+        instructions::top_up_insurance_fund(ctx, &params)
+    }
+
+    pub fn withdraw_insurance_fund(
+        ctx: Context<WithdrawInsuranceFund>,
+        params: WithdrawInsuranceFundParams,
+    ) -> Result<u8> {
+        instructions::withdraw_insurance_fund(ctx, &params)
+    }
+
+    pub fn migrate_perpetuals(
+        ctx: Context<MigratePerpetuals>,
+        params: MigratePerpetualsParams,
+    ) -> Result<u8> {
+        instructions::migrate_perpetuals(ctx, &params)
+    }
+
+    pub fn migrate_pool(ctx: Context<MigratePool>, params: MigratePoolParams) -> Result<u8> {
+        instructions::migrate_pool(ctx, &params)
+    }
+
+    pub fn migrate_custody(
+        ctx: Context<MigrateCustody>,
+        params: MigrateCustodyParams,
+    ) -> Result<u8> {
+        instructions::migrate_custody(ctx, &params)
+    }
+
+    pub fn migrate_position(
+        ctx: Context<MigratePosition>,
+        params: MigratePositionParams,
+    ) -> Result<u8> {
+        instructions::migrate_position(ctx, &params)
+    }
+
     // test instructions
 
     pub fn set_test_oracle_price(
@@ -37,6 +149,19 @@ pub mod perps_demo {
         instructions::set_test_oracle_price(ctx, &params)
     }
 
+    // views
+
+    pub fn get_adl_queue(ctx: Context<GetAdlQueue>, params: GetAdlQueueParams) -> Result<AdlQueue> {
+        instructions::get_adl_queue(ctx, &params)
+    }
+
+    pub fn get_audit_log(
+        ctx: Context<GetAuditLog>,
+        params: GetAuditLogParams,
+    ) -> Result<AuditLogPage> {
+        instructions::get_audit_log(ctx, &params)
+    }
+
     // public instructions
 
     pub fn swap(ctx: Context<Swap>, params: SwapParams) -> Result<()> {
@@ -62,7 +187,55 @@ pub mod perps_demo {
         instructions::close_position(ctx, &params)
     }
 
+    pub fn add_collateral(ctx: Context<AddCollateral>, params: AddCollateralParams) -> Result<()> {
+        instructions::add_collateral(ctx, &params)
+    }
+
+    pub fn remove_collateral(
+        ctx: Context<RemoveCollateral>,
+        params: RemoveCollateralParams,
+    ) -> Result<()> {
+        instructions::remove_collateral(ctx, &params)
+    }
+
+    pub fn create_order(ctx: Context<CreateOrder>, params: CreateOrderParams) -> Result<()> {
+        instructions::create_order(ctx, &params)
+    }
+
+    pub fn execute_order(ctx: Context<ExecuteOrder>, params: ExecuteOrderParams) -> Result<()> {
+        instructions::execute_order(ctx, &params)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, params: CancelOrderParams) -> Result<()> {
+        instructions::cancel_order(ctx, &params)
+    }
+
+    pub fn set_trigger(ctx: Context<SetTrigger>, params: SetTriggerParams) -> Result<()> {
+        instructions::set_trigger(ctx, &params)
+    }
+
+    pub fn execute_trigger(
+        ctx: Context<ExecuteTrigger>,
+        params: ExecuteTriggerParams,
+    ) -> Result<()> {
+        instructions::execute_trigger(ctx, &params)
+    }
+
+    pub fn auto_deleverage(
+        ctx: Context<AutoDeleverage>,
+        params: AutoDeleverageParams,
+    ) -> Result<()> {
+        instructions::auto_deleverage(ctx, &params)
+    }
+
     pub fn liquidate(ctx: Context<Liquidate>, params: LiquidateParams) -> Result<()> {
         instructions::liquidate(ctx, &params)
     }
+
+    pub fn apply_pending_change(
+        ctx: Context<ApplyPendingChange>,
+        params: ApplyPendingChangeParams,
+    ) -> Result<()> {
+        instructions::apply_pending_change(ctx, &params)
+    }
 }
@@ -38,6 +38,21 @@ where
     }
 }
 
+pub fn checked_ceil_div<T>(arg1: T, arg2: T) -> Result<T>
+where
+    T: num_traits::PrimInt + Display,
+{
+    if arg1 == T::zero() {
+        return checked_div(arg1, arg2);
+    }
+    if let Some(res) = (arg1 - T::one()).checked_div(&arg2) {
+        Ok(res + T::one())
+    } else {
+        msg!("Error: Overflow in {} / {}", arg1, arg2);
+        err!(PerpetualsError::MathOverflow)
+    }
+}
+
 pub fn checked_decimal_div(
     coefficient1: u64,
     exponent1: i32,
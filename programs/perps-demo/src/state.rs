@@ -1,7 +1,11 @@
 // Program state handling.
 
+pub mod audit_log;
 pub mod custody;
+pub mod multisig;
 pub mod oracle;
+pub mod order;
+pub mod pending_change;
 pub mod perpetuals;
 pub mod pool;
 pub mod position;
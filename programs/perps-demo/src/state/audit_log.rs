@@ -0,0 +1,121 @@
+use {
+    anchor_lang::prelude::*,
+    solana_program::hash::{hash, hashv},
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum AuditAction {
+    Init,
+    AddPool,
+    RemovePool,
+    AddToken,
+    RemoveToken,
+    SetAdminSigners,
+    ProposeAdmin,
+    AcceptAdmin,
+    CancelAdminProposal,
+    SetRole,
+    SetPermissions,
+    SetDepositCaps,
+    SetCustodyConfig,
+    ApplyPendingChange,
+    CancelPendingChange,
+    SetTimelockDelay,
+    TopUpInsuranceFund,
+    WithdrawInsuranceFund,
+    MigratePerpetuals,
+    MigratePool,
+    MigrateCustody,
+    MigratePosition,
+}
+
+impl Default for AuditAction {
+    fn default() -> Self {
+        Self::Init
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct AuditEntry {
+    // sequence number of the entry since the log was created
+    pub index: u64,
+    pub action: AuditAction,
+    pub signer: Pubkey,
+    pub slot: u64,
+    // hash of the serialized instruction params or applied config
+    pub config_hash: [u8; 32],
+}
+
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct AuditLogPage {
+    pub num_entries: u64,
+    pub entries: Vec<AuditEntry>,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct AuditLog {
+    // total number of recorded entries, older ones are overwritten once the buffer is full
+    pub num_entries: u64,
+    // hash chained over all entries, allows to verify history that is no longer stored
+    pub chain_hash: [u8; 32],
+    pub entries: Vec<AuditEntry>,
+
+    pub bump: u8,
+}
+
+impl AuditLog {
+    pub const MAX_ENTRIES: usize = 64;
+    // keeps view output within the return data limit
+    pub const MAX_PAGE_SIZE: usize = 10;
+    pub const LEN: usize = 8
+        + std::mem::size_of::<AuditLog>()
+        + AuditLog::MAX_ENTRIES * std::mem::size_of::<AuditEntry>();
+
+    /// Appends an entry for the admin action, overwriting the oldest one if the log is full
+    pub fn record<T: AnchorSerialize>(
+        &mut self,
+        action: AuditAction,
+        signer: Pubkey,
+        config: &T,
+    ) -> Result<()> {
+        let entry = AuditEntry {
+            index: self.num_entries,
+            action,
+            signer,
+            slot: Clock::get()?.slot,
+            config_hash: hash(&config.try_to_vec()?).to_bytes(),
+        };
+
+        let pos = (self.num_entries % Self::MAX_ENTRIES as u64) as usize;
+        if pos < self.entries.len() {
+            self.entries[pos] = entry;
+        } else {
+            self.entries.push(entry);
+        }
+        self.chain_hash = hashv(&[&self.chain_hash, &entry.try_to_vec()?]).to_bytes();
+        self.num_entries += 1;
+
+        msg!("Audit log entry: {}", entry.index);
+        Ok(())
+    }
+
+    /// Returns up to `count` stored entries starting from sequence number `start`
+    pub fn get_page(&self, start: u64, count: usize) -> AuditLogPage {
+        let first = std::cmp::max(
+            start,
+            self.num_entries.saturating_sub(Self::MAX_ENTRIES as u64),
+        );
+        let last = std::cmp::min(
+            first.saturating_add(std::cmp::min(count, Self::MAX_PAGE_SIZE) as u64),
+            self.num_entries,
+        );
+
+        AuditLogPage {
+            num_entries: self.num_entries,
+            entries: (first..last)
+                .map(|idx| self.entries[(idx % Self::MAX_ENTRIES as u64) as usize])
+                .collect(),
+        }
+    }
+}
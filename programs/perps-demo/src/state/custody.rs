@@ -1,4 +1,10 @@
-use {crate::state::oracle::OracleType, anchor_lang::prelude::*};
+use {
+    crate::state::{
+        oracle::OracleType,
+        perpetuals::{Permissions, Perpetuals},
+    },
+    anchor_lang::prelude::*,
+};
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Assets {
@@ -19,9 +25,50 @@ pub struct OracleParams {
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct PricingParams {
-    // pricing params have implied BPS_DECIMALS decimals
+    // leverage and utilization params have implied BPS_DECIMALS decimals
     pub min_initial_leverage: u64,
     pub max_leverage: u64,
+    // max share of owned funds that can be locked for profit payoff
+    pub max_utilization: u64,
+    // open interest caps in USD
+    pub max_long_open_interest_usd: u64,
+    pub max_short_open_interest_usd: u64,
+    // position limits in USD
+    pub min_position_size_usd: u64,
+    pub max_position_size_usd: u64,
+    pub min_collateral_usd: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct OpenInterest {
+    // total size of open positions in USD
+    pub long_usd: u64,
+    pub short_usd: u64,
+    // total size of open positions in tokens at their entry prices, with implied
+    // USD_DECIMALS decimals, used to compute aggregate unrealized pnl
+    pub long_tokens: u128,
+    pub short_tokens: u128,
+    // number of open positions
+    pub long_positions: u64,
+    pub short_positions: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct Fees {
+    // fees have implied BPS_DECIMALS decimals and are charged on position size
+    pub open_position: u64,
+    pub close_position: u64,
+    pub liquidation: u64,
+    // share of collected fees sent to the insurance fund
+    pub insurance_share: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyConfig {
+    pub is_stable: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub fees: Fees,
 }
 
 #[account]
@@ -30,11 +77,23 @@ pub struct Custody {
     pub token_account: Pubkey,
     pub mint: Pubkey,
     pub decimals: u8,
+    pub is_stable: bool,
     pub oracle: OracleParams,
     pub pricing: PricingParams,
+    pub fees: Fees,
     pub assets: Assets,
+    // deposits are rejected once owned tokens would exceed this
+    pub max_amount: u64,
+    pub open_interest: OpenInterest,
+    // total liquidation shortfall in tokens, loss beyond position margin that LPs forgo
+    pub bad_debt: u64,
+    pub permissions: Permissions,
     pub bump: u8,
     pub token_account_bump: u8,
+    pub insurance_fund_bump: u8,
+
+    pub version: u8,
+    pub padding: [u64; 8],
 }
 
 impl OracleParams {
@@ -46,16 +105,51 @@ impl OracleParams {
 impl PricingParams {
     pub fn validate(&self) -> bool {
         self.min_initial_leverage <= self.max_leverage
+            && self.min_position_size_usd <= self.max_position_size_usd
+            && self.max_utilization as u128 <= Perpetuals::BPS_POWER
+    }
+}
+
+impl Fees {
+    pub fn validate(&self) -> bool {
+        self.open_position as u128 <= Perpetuals::BPS_POWER
+            && self.close_position as u128 <= Perpetuals::BPS_POWER
+            && self.liquidation as u128 <= Perpetuals::BPS_POWER
+            && self.insurance_share as u128 <= Perpetuals::BPS_POWER
+    }
+}
+
+impl CustodyConfig {
+    pub fn validate(&self) -> bool {
+        self.oracle.validate() && self.pricing.validate() && self.fees.validate()
     }
 }
 
 impl Custody {
     pub const LEN: usize = 8 + std::mem::size_of::<Custody>();
+    pub const VERSION: u8 = 1;
+
+    pub fn get_config(&self) -> CustodyConfig {
+        CustodyConfig {
+            is_stable: self.is_stable,
+            oracle: self.oracle,
+            pricing: self.pricing,
+            fees: self.fees,
+        }
+    }
+
+    pub fn set_config(&mut self, config: &CustodyConfig) {
+        self.is_stable = config.is_stable;
+        self.oracle = config.oracle;
+        self.pricing = config.pricing;
+        self.fees = config.fees;
+    }
 
     pub fn validate(&self) -> bool {
         self.token_account != Pubkey::default()
             && self.mint != Pubkey::default()
             && self.oracle.validate()
             && self.pricing.validate()
+            && self.fees.validate()
     }
 }
@@ -0,0 +1,159 @@
+use {
+    crate::error::PerpetualsError,
+    anchor_lang::prelude::*,
+    solana_program::{hash::hashv, program_error::ProgramError},
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum AdminInstruction {
+    AddPool,
+    AddToken,
+    CancelAdminProposal,
+    CancelPendingChange,
+    MigrateCustody,
+    MigratePerpetuals,
+    MigratePool,
+    MigratePosition,
+    ProposeAdmin,
+    RemovePool,
+    RemoveToken,
+    SetAdminSigners,
+    SetCustodyConfig,
+    SetPermissions,
+    SetRole,
+    SetTimelockDelay,
+    TopUpInsuranceFund,
+    WithdrawInsuranceFund,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Multisig {
+    pub num_signers: u8,
+    pub num_signed: u8,
+    pub min_signatures: u8,
+    // hash of the instruction accounts and data being approved
+    pub instruction_hash: [u8; 32],
+    pub signers: [Pubkey; Multisig::MAX_SIGNERS],
+    pub signed: [bool; Multisig::MAX_SIGNERS],
+
+    pub bump: u8,
+}
+
+impl Multisig {
+    pub const MAX_SIGNERS: usize = 6;
+    pub const LEN: usize = 8 + std::mem::size_of::<Multisig>();
+
+    /// Returns keys of instruction accounts followed by remaining accounts
+    pub fn get_account_keys<'info, T: ToAccountInfos<'info>>(ctx: &Context<T>) -> Vec<Pubkey> {
+        let mut keys: Vec<Pubkey> = ctx
+            .accounts
+            .to_account_infos()
+            .iter()
+            .map(|account| account.key())
+            .collect();
+        keys.extend(ctx.remaining_accounts.iter().map(|account| account.key()));
+        keys
+    }
+
+    /// Returns serialized instruction params prefixed with the instruction type
+    pub fn get_instruction_data<T: AnchorSerialize>(
+        instruction_type: AdminInstruction,
+        params: &T,
+    ) -> Result<Vec<u8>> {
+        let mut data = vec![instruction_type as u8];
+        params.serialize(&mut data)?;
+        Ok(data)
+    }
+
+    /// Replaces signers with the given accounts and resets pending approvals
+    pub fn set_signers(&mut self, signers: &[AccountInfo], min_signatures: u8) -> Result<()> {
+        if signers.is_empty()
+            || signers.len() > Self::MAX_SIGNERS
+            || min_signatures == 0
+            || min_signatures as usize > signers.len()
+        {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+
+        self.signers = [Pubkey::default(); Self::MAX_SIGNERS];
+        for (idx, signer) in signers.iter().enumerate() {
+            if self.signers[..idx].contains(signer.key) {
+                return Err(ProgramError::InvalidArgument.into());
+            }
+            self.signers[idx] = signer.key();
+        }
+        self.num_signers = signers.len() as u8;
+        self.min_signatures = min_signatures;
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Swaps one signer for another keeping the threshold, resets pending approvals
+    pub fn replace_signer(&mut self, signer: &Pubkey, new_signer: &Pubkey) -> Result<()> {
+        if self.signers[..self.num_signers as usize].contains(new_signer) {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+        let signer_idx = self.get_signer_index(signer)?;
+        self.signers[signer_idx] = *new_signer;
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Records a signature for the instruction and returns the number of
+    /// signatures still required. Approvals reset once the threshold is reached.
+    pub fn sign_multisig(
+        &mut self,
+        signer_account: &AccountInfo,
+        instruction_accounts: &[Pubkey],
+        instruction_data: &[u8],
+    ) -> Result<u8> {
+        if !signer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature.into());
+        }
+        let signer_idx = self.get_signer_index(signer_account.key)?;
+
+        // approvals only count for identical instructions, a different one starts over
+        let instruction_hash = Self::get_instruction_hash(instruction_accounts, instruction_data);
+        if instruction_hash != self.instruction_hash {
+            self.reset();
+            self.instruction_hash = instruction_hash;
+        } else if self.signed[signer_idx] {
+            return err!(PerpetualsError::MultisigAlreadySigned);
+        }
+        self.signed[signer_idx] = true;
+        self.num_signed += 1;
+
+        if self.num_signed >= self.min_signatures {
+            self.reset();
+            Ok(0)
+        } else {
+            Ok(self.min_signatures - self.num_signed)
+        }
+    }
+
+    // private helpers
+    fn get_signer_index(&self, signer: &Pubkey) -> Result<usize> {
+        self.signers[..self.num_signers as usize]
+            .iter()
+            .position(|k| k == signer)
+            .ok_or_else(|| PerpetualsError::MultisigAccountNotAuthorized.into())
+    }
+
+    fn get_instruction_hash(instruction_accounts: &[Pubkey], instruction_data: &[u8]) -> [u8; 32] {
+        let mut data: Vec<&[u8]> = instruction_accounts
+            .iter()
+            .map(|key| key.as_ref())
+            .collect();
+        data.push(instruction_data);
+        hashv(&data).to_bytes()
+    }
+
+    fn reset(&mut self) {
+        self.num_signed = 0;
+        self.instruction_hash = [0; 32];
+        self.signed = [false; Self::MAX_SIGNERS];
+    }
+}
@@ -123,6 +123,25 @@ impl OraclePrice {
         })
     }
 
+    /// Returns price rescaled to the target exponent
+    pub fn scale_to_exponent(&self, target_exponent: i32) -> Result<OraclePrice> {
+        if target_exponent == self.exponent {
+            return Ok(*self);
+        }
+        let delta = math::checked_sub(target_exponent, self.exponent)?;
+        if delta > 0 {
+            Ok(OraclePrice {
+                price: math::checked_div(self.price, math::checked_pow(10, delta as usize)?)?,
+                exponent: target_exponent,
+            })
+        } else {
+            Ok(OraclePrice {
+                price: math::checked_mul(self.price, math::checked_pow(10, (-delta) as usize)?)?,
+                exponent: target_exponent,
+            })
+        }
+    }
+
     pub fn checked_div(&self, other: &OraclePrice) -> Result<OraclePrice> {
         let base = self.normalize()?;
         let other = other.normalize()?;
@@ -0,0 +1,31 @@
+use {crate::state::position::Side, anchor_lang::prelude::*};
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Order {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    // chosen by the owner, allows several resting orders per custody and side
+    pub order_id: u64,
+
+    pub time: i64,
+    pub expiration_time: i64,
+    pub side: Side,
+    // position is opened once the position price crosses this value,
+    // at or below it for longs and at or above it for shorts
+    pub trigger_price: u64,
+    // amounts below are in collateral custody tokens
+    pub collateral: u64,
+    pub execution_fee: u64,
+    // position size in custody tokens
+    pub size: u64,
+
+    pub bump: u8,
+    pub token_account_bump: u8,
+}
+
+impl Order {
+    pub const LEN: usize = 8 + std::mem::size_of::<Order>();
+}
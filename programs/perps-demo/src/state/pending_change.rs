@@ -0,0 +1,41 @@
+use {crate::state::custody::CustodyConfig, anchor_lang::prelude::*};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum ChangeType {
+    CustodyConfig,
+    DepositCaps,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DepositCaps {
+    pub max_aum_usd: u128,
+    pub max_amount: u64,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct PendingChange {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    // receives the account rent once the change is applied or cancelled
+    pub payer: Pubkey,
+    pub change_type: ChangeType,
+    // set for custody config changes
+    pub config: CustodyConfig,
+    // set for deposit cap changes
+    pub deposit_caps: DepositCaps,
+    // earliest time the change can be applied
+    pub execute_time: i64,
+
+    pub bump: u8,
+}
+
+impl Default for ChangeType {
+    fn default() -> Self {
+        Self::CustodyConfig
+    }
+}
+
+impl PendingChange {
+    pub const LEN: usize = 8 + std::mem::size_of::<PendingChange>();
+}
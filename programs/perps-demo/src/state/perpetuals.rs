@@ -1,16 +1,75 @@
 use {
-    anchor_lang::prelude::*,
-    anchor_spl::token::{Burn, MintTo, Transfer},
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{custody::Custody, pool::Pool},
+    },
+    anchor_lang::{prelude::*, system_program},
+    anchor_spl::token::{Burn, CloseAccount, MintTo, Transfer},
 };
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum Role {
+    Pauser,
+    ParameterManager,
+    OracleUpdater,
+    // not accepted for insurance fund withdrawals, those need the multisig
+    FeeWithdrawer,
+    Keeper,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct Roles {
+    pub pauser: Pubkey,
+    pub parameter_manager: Pubkey,
+    pub oracle_updater: Pubkey,
+    pub fee_withdrawer: Pubkey,
+    pub keeper: Pubkey,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum Permission {
+    Swap,
+    AddLiquidity,
+    RemoveLiquidity,
+    OpenPosition,
+    ClosePosition,
+    Liquidation,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub struct Permissions {
+    pub allow_swap: bool,
+    pub allow_add_liquidity: bool,
+    pub allow_remove_liquidity: bool,
+    pub allow_open_position: bool,
+    pub allow_close_position: bool,
+    pub allow_liquidation: bool,
+}
+
 #[account]
 #[derive(Default, Debug)]
 pub struct Perpetuals {
+    // holds a multisig seat, accept_admin hands it to the new admin
     pub admin: Pubkey,
+    // proposed admin, takes over once it signs accept_admin
+    pub pending_admin: Pubkey,
+    pub roles: Roles,
+    pub permissions: Permissions,
+    // delay before queued custody config and deposit cap changes can be applied
+    pub timelock_delay_sec: i64,
+    // reduced delay, takes effect once pending_timelock_delay_time has passed
+    pub pending_timelock_delay_sec: i64,
+    pub pending_timelock_delay_time: i64,
     pub pools: Vec<Pubkey>,
 
     pub transfer_authority_bump: u8,
     pub perpetuals_bump: u8,
+
+    // layout version and space reserved for new fields, pool, custody and position
+    // end with the same pair, accounts created before versioning are upgraded with migrate_*
+    pub version: u8,
+    pub padding: [u64; 8],
 }
 
 impl anchor_lang::Id for Perpetuals {
@@ -19,11 +78,90 @@ impl anchor_lang::Id for Perpetuals {
     }
 }
 
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            allow_swap: true,
+            allow_add_liquidity: true,
+            allow_remove_liquidity: true,
+            allow_open_position: true,
+            allow_close_position: true,
+            allow_liquidation: true,
+        }
+    }
+}
+
+impl Permissions {
+    /// Fails with the error of the permission flag if it is not set
+    pub fn check(&self, permission: Permission) -> Result<()> {
+        match permission {
+            Permission::Swap => require!(self.allow_swap, PerpetualsError::SwapNotAllowed),
+            Permission::AddLiquidity => require!(
+                self.allow_add_liquidity,
+                PerpetualsError::AddLiquidityNotAllowed
+            ),
+            Permission::RemoveLiquidity => require!(
+                self.allow_remove_liquidity,
+                PerpetualsError::RemoveLiquidityNotAllowed
+            ),
+            Permission::OpenPosition => require!(
+                self.allow_open_position,
+                PerpetualsError::OpenPositionNotAllowed
+            ),
+            Permission::ClosePosition => require!(
+                self.allow_close_position,
+                PerpetualsError::ClosePositionNotAllowed
+            ),
+            Permission::Liquidation => require!(
+                self.allow_liquidation,
+                PerpetualsError::LiquidationNotAllowed
+            ),
+        }
+        Ok(())
+    }
+
+    /// Returns true if everything allowed by other is also allowed here
+    pub fn contains(&self, other: &Permissions) -> bool {
+        (self.allow_swap || !other.allow_swap)
+            && (self.allow_add_liquidity || !other.allow_add_liquidity)
+            && (self.allow_remove_liquidity || !other.allow_remove_liquidity)
+            && (self.allow_open_position || !other.allow_open_position)
+            && (self.allow_close_position || !other.allow_close_position)
+            && (self.allow_liquidation || !other.allow_liquidation)
+    }
+}
+
+impl Roles {
+    pub fn get(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Pauser => self.pauser,
+            Role::ParameterManager => self.parameter_manager,
+            Role::OracleUpdater => self.oracle_updater,
+            Role::FeeWithdrawer => self.fee_withdrawer,
+            Role::Keeper => self.keeper,
+        }
+    }
+
+    pub fn set(&mut self, role: Role, account: Pubkey) {
+        match role {
+            Role::Pauser => self.pauser = account,
+            Role::ParameterManager => self.parameter_manager = account,
+            Role::OracleUpdater => self.oracle_updater = account,
+            Role::FeeWithdrawer => self.fee_withdrawer = account,
+            Role::Keeper => self.keeper = account,
+        }
+    }
+}
+
 impl Perpetuals {
     pub const LEN: usize = 8 + std::mem::size_of::<Perpetuals>();
+    pub const VERSION: u8 = 1;
+    // serialized size of the version and padding fields added to legacy accounts
+    pub const VERSION_SPACE: usize = 1 + std::mem::size_of::<[u64; 8]>();
     pub const BPS_DECIMALS: u8 = 4;
     pub const BPS_POWER: u128 = 10i64.pow(Self::BPS_DECIMALS as u32) as u128;
     pub const PRICE_DECIMALS: u8 = 6;
+    pub const PRICE_POWER: u128 = 10i64.pow(Self::PRICE_DECIMALS as u32) as u128;
     pub const USD_DECIMALS: u8 = 6;
     pub const LP_DECIMALS: u8 = Self::USD_DECIMALS;
 
@@ -36,6 +174,49 @@ impl Perpetuals {
         }
     }
 
+    /// Returns the delay in effect, a queued reduction applies once its time has passed
+    pub fn get_timelock_delay(&self) -> Result<i64> {
+        if self.pending_timelock_delay_time > 0
+            && self.get_time()? >= self.pending_timelock_delay_time
+        {
+            Ok(self.pending_timelock_delay_sec)
+        } else {
+            Ok(self.timelock_delay_sec)
+        }
+    }
+
+    /// Returns true if the account holds the role, roles are granted by the multisig
+    pub fn has_role(&self, account: &Pubkey, role: Role) -> bool {
+        *account == self.roles.get(role)
+    }
+
+    /// Fails if the action is paused globally, for the pool or for any of the custodies
+    pub fn check_permission(
+        &self,
+        permission: Permission,
+        pool: &Pool,
+        custodies: &[&Custody],
+    ) -> Result<()> {
+        self.permissions.check(permission)?;
+        pool.permissions.check(permission)?;
+        for custody in custodies {
+            custody.permissions.check(permission)?;
+        }
+        Ok(())
+    }
+
+    /// Fails if the current time is past the optional user deadline
+    pub fn check_deadline(&self, deadline: Option<i64>) -> Result<()> {
+        if let Some(deadline) = deadline {
+            require_gte!(
+                deadline,
+                self.get_time()?,
+                PerpetualsError::DeadlineExceeded
+            );
+        }
+        Ok(())
+    }
+
     pub fn transfer_tokens<'info>(
         &self,
         from: AccountInfo<'info>,
@@ -122,4 +303,103 @@ impl Perpetuals {
 
         anchor_spl::token::burn(context, amount)
     }
+
+    pub fn close_token_account<'info>(
+        &self,
+        receiver: AccountInfo<'info>,
+        token_account: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+    ) -> Result<()> {
+        let authority_seeds: &[&[&[u8]]] =
+            &[&[b"transfer_authority", &[self.transfer_authority_bump]]];
+
+        let context = CpiContext::new(
+            token_program,
+            CloseAccount {
+                account: token_account,
+                destination: receiver,
+                authority,
+            },
+        )
+        .with_signer(authority_seeds);
+
+        anchor_spl::token::close_account(context)
+    }
+
+    /// Deserializes legacy account without modifying it, the version and padding fields
+    /// missing from legacy accounts read as zero so the account reads as version 0.
+    /// Only the layout right before versioning is supported, the current fields without
+    /// version and padding. Accounts written with an older field set are not detected
+    /// reliably and need a dedicated decoder before they can be migrated.
+    pub fn load_legacy_account<T: AccountDeserialize + Owner>(account: AccountInfo) -> Result<T> {
+        if account.owner != &T::owner() {
+            return Err(ProgramError::IllegalOwner.into());
+        }
+        let mut data = account.try_borrow_data()?.to_vec();
+        data.resize(math::checked_add(data.len(), Self::VERSION_SPACE)?, 0);
+
+        // checks the discriminator
+        T::try_deserialize(&mut &data[..])
+    }
+
+    /// Grows legacy account to fit the version and padding fields, appended data is zeroed
+    pub fn grow_legacy_account<'info>(
+        account: AccountInfo<'info>,
+        payer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+    ) -> Result<()> {
+        let new_len = math::checked_add(account.data_len(), Self::VERSION_SPACE)?;
+        Self::grow_account(account, payer, system_program, new_len)
+    }
+
+    /// Grows program owned account, the payer funds the additional rent
+    pub fn grow_account<'info>(
+        account: AccountInfo<'info>,
+        payer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        new_len: usize,
+    ) -> Result<()> {
+        if new_len <= account.data_len() {
+            return Ok(());
+        }
+
+        let min_balance = Rent::get()?.minimum_balance(new_len);
+        let shortfall = min_balance.saturating_sub(account.lamports());
+        if shortfall > 0 {
+            let context = CpiContext::new(
+                system_program,
+                system_program::Transfer {
+                    from: payer,
+                    to: account.clone(),
+                },
+            );
+            system_program::transfer(context, shortfall)?;
+        }
+
+        account.realloc(new_len, true)?;
+
+        Ok(())
+    }
+
+    /// Shrinks program owned account and returns released rent to the receiver. The data
+    /// is zeroed so no stale bytes are left past the end of the account serialized on exit
+    pub fn shrink_account<'info>(
+        account: AccountInfo<'info>,
+        receiver: AccountInfo<'info>,
+        new_len: usize,
+    ) -> Result<()> {
+        if new_len >= account.data_len() {
+            return Ok(());
+        }
+        account.realloc(new_len, false)?;
+        account.try_borrow_mut_data()?.fill(0);
+
+        let min_balance = Rent::get()?.minimum_balance(new_len);
+        let excess = account.lamports().saturating_sub(min_balance);
+        **account.try_borrow_mut_lamports()? -= excess;
+        **receiver.try_borrow_mut_lamports()? += excess;
+
+        Ok(())
+    }
 }
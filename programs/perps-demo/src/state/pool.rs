@@ -2,9 +2,15 @@ use {
     crate::{
         error::PerpetualsError,
         math,
-        state::{custody::Custody, oracle::OraclePrice},
+        state::{
+            custody::Custody,
+            oracle::OraclePrice,
+            perpetuals::{Permissions, Perpetuals},
+            position::{Position, Side},
+        },
     },
     anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
 };
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -12,20 +18,42 @@ pub struct PoolToken {
     pub custody: Pubkey,
 }
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct AdlQueueEntry {
+    pub position: Pubkey,
+    pub profit_usd: u64,
+    pub leverage: u64,
+}
+
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct AdlQueue {
+    // net unrealized profit of all positions of the custody side in collateral tokens
+    pub liabilities: u64,
+    pub assets: u64,
+    pub positions: Vec<AdlQueueEntry>,
+}
+
 #[account]
 #[derive(Default, Debug)]
 pub struct Pool {
     pub name: String,
     pub tokens: Vec<PoolToken>,
     pub aum_usd: u128,
+    // deposits are rejected once AUM would exceed this
+    pub max_aum_usd: u128,
+    pub permissions: Permissions,
 
     pub bump: u8,
     pub lp_token_bump: u8,
+
+    pub version: u8,
+    pub padding: [u64; 8],
 }
 
 /// Token Pool
 impl Pool {
     pub const LEN: usize = 8 + std::mem::size_of::<Pool>();
+    pub const VERSION: u8 = 1;
 
     pub fn get_token_id(&self, custody: &Pubkey) -> Result<usize> {
         self.tokens
@@ -60,13 +88,381 @@ impl Pool {
         )
     }
 
+    /// Longs are backed by the traded token, shorts of a non-stable token by a stable token
+    pub fn check_collateral_custody(
+        &self,
+        side: Side,
+        custody: &Account<Custody>,
+        collateral_custody: &Account<Custody>,
+    ) -> Result<()> {
+        if side == Side::Long {
+            require_keys_eq!(
+                custody.key(),
+                collateral_custody.key(),
+                PerpetualsError::InvalidCollateralCustody
+            );
+        } else {
+            // both custodies are written back separately, open interest would be lost
+            // if they were the same account
+            require_keys_neq!(
+                custody.key(),
+                collateral_custody.key(),
+                PerpetualsError::InvalidCollateralCustody
+            );
+            require!(
+                !custody.is_stable && collateral_custody.is_stable,
+                PerpetualsError::InvalidCollateralCustody
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns position price with implied PRICE_DECIMALS decimals
+    pub fn get_position_price(&self, token_price: &OraclePrice) -> Result<u64> {
+        Ok(token_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price)
+    }
+
+    /// Returns unrealized (profit, loss) of the position in USD
+    pub fn get_pnl_usd(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+    ) -> Result<(u64, u64)> {
+        if position.size_usd == 0 || position.price == 0 {
+            return Ok((0, 0));
+        }
+        let exit_price = self.get_position_price(token_price)?;
+
+        let (price_diff_profit, price_diff_loss) = if position.side == Side::Long {
+            if exit_price > position.price {
+                (math::checked_sub(exit_price, position.price)?, 0u64)
+            } else {
+                (0u64, math::checked_sub(position.price, exit_price)?)
+            }
+        } else if exit_price < position.price {
+            (math::checked_sub(position.price, exit_price)?, 0u64)
+        } else {
+            (0u64, math::checked_sub(exit_price, position.price)?)
+        };
+
+        let profit_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(position.size_usd as u128, price_diff_profit as u128)?,
+            position.price as u128,
+        )?)?;
+        let loss_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(position.size_usd as u128, price_diff_loss as u128)?,
+            position.price as u128,
+        )?)?;
+
+        Ok((profit_usd, loss_usd))
+    }
+
+    /// Returns current position leverage with implied BPS_DECIMALS decimals
+    pub fn get_leverage(&self, position: &Position, token_price: &OraclePrice) -> Result<u64> {
+        let (profit_usd, loss_usd) = self.get_pnl_usd(position, token_price)?;
+        let margin_usd = math::checked_add(position.collateral_usd, profit_usd)?;
+        if margin_usd <= loss_usd {
+            return Ok(u64::MAX);
+        }
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(position.size_usd as u128, Perpetuals::BPS_POWER)?,
+            math::checked_sub(margin_usd, loss_usd)? as u128,
+        )?)
+    }
+
+    pub fn check_leverage(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        custody: &Custody,
+        initial: bool,
+    ) -> Result<bool> {
+        let leverage = self.get_leverage(position, token_price)?;
+        Ok(leverage <= custody.pricing.max_leverage
+            && (!initial || leverage >= custody.pricing.min_initial_leverage))
+    }
+
+    /// Checks position size and collateral against custody limits
+    pub fn check_position_size(&self, position: &Position, custody: &Custody) -> Result<()> {
+        require_gte!(
+            position.size_usd,
+            custody.pricing.min_position_size_usd,
+            PerpetualsError::MinPositionSize
+        );
+        require_gte!(
+            custody.pricing.max_position_size_usd,
+            position.size_usd,
+            PerpetualsError::MaxPositionSize
+        );
+        require_gte!(
+            position.collateral_usd,
+            custody.pricing.min_collateral_usd,
+            PerpetualsError::MinCollateral
+        );
+        Ok(())
+    }
+
+    /// Extends a partial close to the whole position if it would leave dust behind
+    pub fn get_close_size(
+        &self,
+        position: &Position,
+        custody: &Custody,
+        size_usd: u64,
+    ) -> Result<u64> {
+        let remaining_size_usd = math::checked_sub(position.size_usd, size_usd)?;
+        let remaining_collateral_usd = math::checked_sub(
+            position.collateral_usd,
+            Self::get_share(position.collateral_usd, size_usd, position.size_usd)?,
+        )?;
+        if remaining_size_usd < custody.pricing.min_position_size_usd
+            || remaining_collateral_usd < custody.pricing.min_collateral_usd
+        {
+            Ok(position.size_usd)
+        } else {
+            Ok(size_usd)
+        }
+    }
+
+    /// Returns the amount of collateral tokens to pay out when
+    /// size_usd of the position is closed at the current price,
+    /// profit of the closed size is cut by up to haircut_usd
+    pub fn get_close_amount(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        collateral_token_price: &OraclePrice,
+        collateral_custody: &Custody,
+        size_usd: u64,
+        haircut_usd: u64,
+    ) -> Result<u64> {
+        let (profit_usd, loss_usd) = self.get_pnl_usd(position, token_price)?;
+        let margin_usd = math::checked_add(position.collateral_usd, profit_usd)?;
+        let available_usd = if margin_usd > loss_usd {
+            math::checked_sub(margin_usd, loss_usd)?
+        } else {
+            0
+        };
+        let close_amount_usd = math::checked_sub(
+            Self::get_share(available_usd, size_usd, position.size_usd)?,
+            std::cmp::min(
+                haircut_usd,
+                Self::get_share(profit_usd, size_usd, position.size_usd)?,
+            ),
+        )?;
+        collateral_token_price.get_token_amount(close_amount_usd, collateral_custody.decimals)
+    }
+
+    /// Returns the fee in collateral tokens for the given fee rate
+    /// applied to size_usd
+    pub fn get_fee_amount(
+        &self,
+        fee: u64,
+        size_usd: u64,
+        collateral_token_price: &OraclePrice,
+        collateral_custody: &Custody,
+    ) -> Result<u64> {
+        let fee_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(size_usd as u128, fee as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        collateral_token_price.get_token_amount(fee_usd, collateral_custody.decimals)
+    }
+
+    /// Returns the insurance fund share of the fee
+    pub fn get_insurance_amount(&self, fee_amount: u64, custody: &Custody) -> Result<u64> {
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(fee_amount as u128, custody.fees.insurance_share as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)
+    }
+
+    /// Keeps the fee in the collateral custody and returns
+    /// the insurance fund share to be transferred out
+    pub fn collect_fee(
+        &self,
+        fee_amount: u64,
+        custody: &Custody,
+        collateral_custody: &mut Custody,
+    ) -> Result<u64> {
+        let insurance_amount = self.get_insurance_amount(fee_amount, custody)?;
+        collateral_custody.assets.owned = math::checked_add(
+            collateral_custody.assets.owned,
+            math::checked_sub(fee_amount, insurance_amount)?,
+        )?;
+        Ok(insurance_amount)
+    }
+
+    /// Adds size and collateral net of the open fee to the position, blending
+    /// the entry price by size, then checks position risk and locks funds for
+    /// profit payoff. Returns the insurance fund share of the fee.
+    #[allow(clippy::too_many_arguments)]
+    pub fn increase_position(
+        &self,
+        position: &mut Position,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+        token_price: &OraclePrice,
+        collateral_token_price: &OraclePrice,
+        collateral: u64,
+        size: u64,
+    ) -> Result<u64> {
+        // compute amount to lock
+        let position_price = self.get_position_price(token_price)?;
+        let size_usd = token_price.get_asset_amount_usd(size, custody.decimals)?;
+        let fee_amount = self.get_fee_amount(
+            custody.fees.open_position,
+            size_usd,
+            collateral_token_price,
+            collateral_custody,
+        )?;
+        require_gt!(
+            collateral,
+            fee_amount,
+            PerpetualsError::InsufficientCollateral
+        );
+        let collateral = math::checked_sub(collateral, fee_amount)?;
+        let collateral_usd =
+            collateral_token_price.get_asset_amount_usd(collateral, collateral_custody.decimals)?;
+        let locked_amount =
+            collateral_token_price.get_token_amount(size_usd, collateral_custody.decimals)?;
+        msg!("Size USD: {}", size_usd);
+        msg!("Collateral USD: {}", collateral_usd);
+        msg!("Locked amount: {}", locked_amount);
+        msg!("Fee amount: {}", fee_amount);
+
+        // positions don't accrue interest yet, so there is nothing
+        // to settle before the size changes
+
+        // take the position out of open interest while it changes
+        self.remove_open_interest(position, custody, collateral_custody)?;
+
+        // blend entry price weighted by size
+        let new_size_usd = math::checked_add(position.size_usd, size_usd)?;
+        position.price = math::checked_as_u64(math::checked_div(
+            math::checked_add(
+                math::checked_mul(position.price as u128, position.size_usd as u128)?,
+                math::checked_mul(position_price as u128, size_usd as u128)?,
+            )?,
+            new_size_usd as u128,
+        )?)?;
+        msg!("Average entry price: {}", position.price);
+
+        position.size_usd = new_size_usd;
+        position.collateral_usd = math::checked_add(position.collateral_usd, collateral_usd)?;
+        position.collateral_amount = math::checked_add(position.collateral_amount, collateral)?;
+        position.locked_amount = math::checked_add(position.locked_amount, locked_amount)?;
+
+        // check position risk
+        msg!("Check position risks");
+        require!(
+            self.check_leverage(position, token_price, custody, true)?,
+            PerpetualsError::MaxLeverage
+        );
+        self.check_position_size(position, custody)?;
+
+        // lock funds for potential profit payoff
+        self.lock_funds(locked_amount, collateral_custody)?;
+
+        // update open interest and check the cap
+        self.add_open_interest(position, custody, collateral_custody)?;
+        self.check_open_interest(position.side, custody, collateral_custody)?;
+
+        // update custody stats
+        collateral_custody.assets.collateral =
+            math::checked_add(collateral_custody.assets.collateral, collateral)?;
+
+        self.collect_fee(fee_amount, custody, collateral_custody)
+    }
+
+    /// Removes size_usd and a matching share of collateral from the position,
+    /// unlocks pool funds and returns the amount of collateral tokens to pay out.
+    /// Profit of the closed size is cut by up to haircut_usd, the cut stays with the pool
+    #[allow(clippy::too_many_arguments)]
+    pub fn decrease_position(
+        &self,
+        position: &mut Position,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+        token_price: &OraclePrice,
+        collateral_token_price: &OraclePrice,
+        size_usd: u64,
+        haircut_usd: u64,
+    ) -> Result<u64> {
+        // compute amount to close
+        let transfer_amount = self.get_close_amount(
+            position,
+            token_price,
+            collateral_token_price,
+            collateral_custody,
+            size_usd,
+            haircut_usd,
+        )?;
+        let collateral_usd = Self::get_share(position.collateral_usd, size_usd, position.size_usd)?;
+        let collateral_amount =
+            Self::get_share(position.collateral_amount, size_usd, position.size_usd)?;
+        let locked_amount = Self::get_share(position.locked_amount, size_usd, position.size_usd)?;
+        msg!("Size USD: {}", size_usd);
+        msg!("Amount out: {}", transfer_amount);
+
+        // unlock pool funds
+        self.unlock_funds(locked_amount, collateral_custody)?;
+        self.remove_open_interest(position, custody, collateral_custody)?;
+
+        // update custody stats
+        msg!("Update custody stats");
+        collateral_custody.assets.collateral =
+            math::checked_sub(collateral_custody.assets.collateral, collateral_amount)?;
+        if transfer_amount > collateral_amount {
+            collateral_custody.assets.owned = math::checked_sub(
+                collateral_custody.assets.owned,
+                math::checked_sub(transfer_amount, collateral_amount)?,
+            )?;
+        } else {
+            collateral_custody.assets.owned = math::checked_add(
+                collateral_custody.assets.owned,
+                math::checked_sub(collateral_amount, transfer_amount)?,
+            )?;
+        }
+
+        // update position
+        position.size_usd = math::checked_sub(position.size_usd, size_usd)?;
+        position.collateral_usd = math::checked_sub(position.collateral_usd, collateral_usd)?;
+        position.collateral_amount =
+            math::checked_sub(position.collateral_amount, collateral_amount)?;
+        position.locked_amount = math::checked_sub(position.locked_amount, locked_amount)?;
+
+        // add what is left of the position back to open interest,
+        // closes don't check the cap so they can't be blocked by it
+        self.add_open_interest(position, custody, collateral_custody)?;
+
+        Ok(transfer_amount)
+    }
+
     pub fn lock_funds(&self, amount: u64, custody: &mut Custody) -> Result<()> {
         custody.assets.locked = math::checked_add(custody.assets.locked, amount)?;
-        if custody.assets.owned < custody.assets.locked {
-            Err(ProgramError::InsufficientFunds.into())
-        } else {
-            Ok(())
+        if custody.assets.locked > custody.assets.owned {
+            return Err(ProgramError::InsufficientFunds.into());
         }
+        let max_locked = math::checked_div(
+            math::checked_mul(
+                custody.assets.owned as u128,
+                custody.pricing.max_utilization as u128,
+            )?,
+            Perpetuals::BPS_POWER,
+        )?;
+        require_gte!(
+            max_locked,
+            custody.assets.locked as u128,
+            PerpetualsError::MaxUtilization
+        );
+        Ok(())
+    }
+
+    /// Returns owned funds that are not locked for profit payoff
+    pub fn get_available_amount(&self, custody: &Custody) -> u64 {
+        custody.assets.owned.saturating_sub(custody.assets.locked)
     }
 
     pub fn unlock_funds(&self, amount: u64, custody: &mut Custody) -> Result<()> {
@@ -78,6 +474,198 @@ impl Pool {
         Ok(())
     }
 
+    /// Returns aggregate unrealized (profit, loss) in USD of all open
+    /// positions of the side, computed from custody open interest
+    pub fn get_open_interest_pnl_usd(
+        &self,
+        side: Side,
+        custody: &Custody,
+        token_price: &OraclePrice,
+    ) -> Result<(u64, u64)> {
+        let (size_usd, size_tokens) = if side == Side::Long {
+            (
+                custody.open_interest.long_usd,
+                custody.open_interest.long_tokens,
+            )
+        } else {
+            (
+                custody.open_interest.short_usd,
+                custody.open_interest.short_tokens,
+            )
+        };
+        let current_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(size_tokens, self.get_position_price(token_price)? as u128)?,
+            Perpetuals::PRICE_POWER,
+        )?)?;
+
+        if (side == Side::Long) == (current_usd > size_usd) {
+            Ok((current_usd.abs_diff(size_usd), 0))
+        } else {
+            Ok((0, current_usd.abs_diff(size_usd)))
+        }
+    }
+
+    /// Ranks the given profitable positions of the custody side for auto-deleveraging,
+    /// highest profit first and highest leverage next. Liabilities are taken from
+    /// custody open interest, so accounts only need to hold the candidates supplied
+    /// by the keeper. Only positions backed by collateral_custody are ranked.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_adl_queue(
+        &self,
+        accounts: &[AccountInfo],
+        side: Side,
+        custody: &Account<Custody>,
+        collateral_custody: &Account<Custody>,
+        token_price: &OraclePrice,
+        collateral_token_price: &OraclePrice,
+    ) -> Result<AdlQueue> {
+        let (profit_usd, _) = self.get_open_interest_pnl_usd(side, custody, token_price)?;
+        let mut queue = AdlQueue {
+            liabilities: collateral_token_price
+                .get_token_amount(profit_usd, collateral_custody.decimals)?,
+            assets: collateral_custody.assets.owned,
+            ..AdlQueue::default()
+        };
+
+        let mut keys: Vec<Pubkey> = Vec::with_capacity(accounts.len());
+        for account in accounts {
+            let position = Account::<Position>::try_from(account)?;
+            require!(
+                position.custody == custody.key() && position.side == side,
+                PerpetualsError::InvalidAdlPosition
+            );
+            require!(
+                !keys.contains(&position.key()),
+                PerpetualsError::InvalidAdlPosition
+            );
+            keys.push(position.key());
+
+            if position.collateral_custody != collateral_custody.key() {
+                continue;
+            }
+            let (profit_usd, _) = self.get_pnl_usd(&position, token_price)?;
+            if profit_usd == 0 {
+                continue;
+            }
+            queue.positions.push(AdlQueueEntry {
+                position: position.key(),
+                profit_usd,
+                leverage: self.get_leverage(&position, token_price)?,
+            });
+        }
+        queue
+            .positions
+            .sort_by(|a, b| (b.profit_usd, b.leverage).cmp(&(a.profit_usd, a.leverage)));
+        Ok(queue)
+    }
+
+    /// Adds the position to custody open interest, called after the position changes
+    pub fn add_open_interest(
+        &self,
+        position: &Position,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+    ) -> Result<()> {
+        if position.size_usd == 0 {
+            return Ok(());
+        }
+        let size_tokens = Self::get_size_tokens(position)?;
+        let open_interest =
+            &mut Self::get_open_interest_custody(position.side, custody, collateral_custody)
+                .open_interest;
+        if position.side == Side::Long {
+            open_interest.long_usd = math::checked_add(open_interest.long_usd, position.size_usd)?;
+            open_interest.long_tokens = math::checked_add(open_interest.long_tokens, size_tokens)?;
+            open_interest.long_positions = math::checked_add(open_interest.long_positions, 1)?;
+        } else {
+            open_interest.short_usd =
+                math::checked_add(open_interest.short_usd, position.size_usd)?;
+            open_interest.short_tokens =
+                math::checked_add(open_interest.short_tokens, size_tokens)?;
+            open_interest.short_positions = math::checked_add(open_interest.short_positions, 1)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the position from custody open interest, called before the position changes
+    pub fn remove_open_interest(
+        &self,
+        position: &Position,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+    ) -> Result<()> {
+        if position.size_usd == 0 {
+            return Ok(());
+        }
+        let size_tokens = Self::get_size_tokens(position)?;
+        let open_interest =
+            &mut Self::get_open_interest_custody(position.side, custody, collateral_custody)
+                .open_interest;
+        if position.side == Side::Long {
+            open_interest.long_usd = open_interest.long_usd.saturating_sub(position.size_usd);
+            open_interest.long_tokens = open_interest.long_tokens.saturating_sub(size_tokens);
+            open_interest.long_positions = open_interest.long_positions.saturating_sub(1);
+        } else {
+            open_interest.short_usd = open_interest.short_usd.saturating_sub(position.size_usd);
+            open_interest.short_tokens = open_interest.short_tokens.saturating_sub(size_tokens);
+            open_interest.short_positions = open_interest.short_positions.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    pub fn check_open_interest(
+        &self,
+        side: Side,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+    ) -> Result<()> {
+        let custody = Self::get_open_interest_custody(side, custody, collateral_custody);
+        if side == Side::Long {
+            require_gte!(
+                custody.pricing.max_long_open_interest_usd,
+                custody.open_interest.long_usd,
+                PerpetualsError::MaxOpenInterest
+            );
+        } else {
+            require_gte!(
+                custody.pricing.max_short_open_interest_usd,
+                custody.open_interest.short_usd,
+                PerpetualsError::MaxOpenInterest
+            );
+        }
+        Ok(())
+    }
+
+    // private helpers
+    // open interest is tracked on the traded token custody, for longs it is the
+    // same account as the collateral custody, which is the copy that gets saved
+    fn get_open_interest_custody<'a>(
+        side: Side,
+        custody: &'a mut Custody,
+        collateral_custody: &'a mut Custody,
+    ) -> &'a mut Custody {
+        if side == Side::Long {
+            collateral_custody
+        } else {
+            custody
+        }
+    }
+
+    // position size in tokens at the entry price, with implied USD_DECIMALS decimals
+    fn get_size_tokens(position: &Position) -> Result<u128> {
+        math::checked_div(
+            math::checked_mul(position.size_usd as u128, Perpetuals::PRICE_POWER)?,
+            position.price as u128,
+        )
+    }
+
+    fn get_share(amount: u64, size_usd: u64, total_size_usd: u64) -> Result<u64> {
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(amount as u128, size_usd as u128)?,
+            total_size_usd as u128,
+        )?)
+    }
+
     pub fn get_assets_under_management_usd(
         &self,
         accounts: &[AccountInfo],
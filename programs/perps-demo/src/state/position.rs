@@ -18,17 +18,54 @@ impl Default for Side {
 pub struct Position {
     pub owner: Pubkey,
     pub pool: Pubkey,
-    pub token_id: u16,
+    // custody of the traded token, its oracle is used for pnl
+    pub custody: Pubkey,
+    // custody that holds the collateral and locked funds,
+    // same as custody for longs and a stable custody for shorts
+    pub collateral_custody: Pubkey,
 
     pub time: i64,
     pub side: Side,
     pub price: u64,
     pub size_usd: u64,
     pub collateral_usd: u64,
+    // amounts below are in collateral custody tokens
+    pub collateral_amount: u64,
+    pub locked_amount: u64,
+
+    // conditional close set by the owner, zero price means not set
+    pub stop_loss_price: u64,
+    pub take_profit_price: u64,
+    // size to close once triggered, the whole position if zero
+    pub trigger_size_usd: u64,
+    // paid to the trigger executor out of the position payout
+    pub trigger_fee_usd: u64,
 
     pub bump: u8,
+
+    pub version: u8,
+    pub padding: [u64; 8],
 }
 
 impl Position {
     pub const LEN: usize = 8 + std::mem::size_of::<Position>();
+    pub const VERSION: u8 = 1;
+
+    /// Checks if the stop-loss or take-profit price is crossed
+    pub fn is_triggered(&self, exit_price: u64) -> bool {
+        if self.side == Side::Long {
+            (self.stop_loss_price > 0 && exit_price <= self.stop_loss_price)
+                || (self.take_profit_price > 0 && exit_price >= self.take_profit_price)
+        } else {
+            (self.stop_loss_price > 0 && exit_price >= self.stop_loss_price)
+                || (self.take_profit_price > 0 && exit_price <= self.take_profit_price)
+        }
+    }
+
+    pub fn clear_triggers(&mut self) {
+        self.stop_loss_price = 0;
+        self.take_profit_price = 0;
+        self.trigger_size_usd = 0;
+        self.trigger_fee_usd = 0;
+    }
 }
@@ -1,10 +1,28 @@
 import { TestClient } from "./test_client";
 import { expect } from "chai";
 import { BN } from "bn.js";
+import * as spl from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
 
 describe("perpsDemo", () => {
   let tc = new TestClient();
   let custodyExpected;
+  let oracleConfig;
+  let pricingConfig;
+  let feesConfig;
+  let keeper;
+
+  // fails the test unless the transaction is rejected with the given error
+  let expectError = async (promise: Promise<void>, code: string) => {
+    let failed = false;
+    try {
+      await promise;
+    } catch (err) {
+      failed = true;
+      expect(err.error.errorCode.code).to.equal(code);
+    }
+    expect(failed).to.be.true;
+  };
 
   it("init", async () => {
     // init
@@ -14,9 +32,30 @@ describe("perpsDemo", () => {
     // verify
     let perpetualsExpected = {
       admin: tc.admin.publicKey,
+      pendingAdmin: PublicKey.default,
+      roles: {
+        pauser: PublicKey.default,
+        parameterManager: PublicKey.default,
+        oracleUpdater: PublicKey.default,
+        feeWithdrawer: PublicKey.default,
+        keeper: PublicKey.default,
+      },
+      permissions: {
+        allowSwap: true,
+        allowAddLiquidity: true,
+        allowRemoveLiquidity: true,
+        allowOpenPosition: true,
+        allowClosePosition: true,
+        allowLiquidation: true,
+      },
+      timelockDelaySec: "0",
+      pendingTimelockDelaySec: "0",
+      pendingTimelockDelayTime: "0",
       pools: [],
       transferAuthorityBump: tc.authority.bump,
       perpetualsBump: tc.perpetuals.bump,
+      version: 1,
+      padding: ["0", "0", "0", "0", "0", "0", "0", "0"],
     };
     let perpetuals = await tc.program.account.perpetuals.fetch(
       tc.perpetuals.publicKey
@@ -24,11 +63,22 @@ describe("perpsDemo", () => {
     expect(JSON.stringify(perpetuals)).to.equal(
       JSON.stringify(perpetualsExpected)
     );
+
+    // roles are only held once the multisig grants them
+    for (let role of Object.keys(perpetualsExpected.roles)) {
+      await tc.setRole({ [role]: {} }, tc.admin.publicKey);
+    }
+    perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    for (let account of Object.values(perpetuals.roles)) {
+      expect(account.toBase58()).to.equal(tc.admin.publicKey.toBase58());
+    }
   });
 
   it("addPool", async () => {
     // add
-    await tc.addPool("test pool");
+    await tc.addPool("test pool", new BN(100000000000));
 
     // verify
     let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
@@ -36,29 +86,92 @@ describe("perpsDemo", () => {
       name: "test pool",
       tokens: [],
       aumUsd: "0",
+      maxAumUsd: "100000000000",
+      permissions: {
+        allowSwap: true,
+        allowAddLiquidity: true,
+        allowRemoveLiquidity: true,
+        allowOpenPosition: true,
+        allowClosePosition: true,
+        allowLiquidation: true,
+      },
       bump: tc.pool.bump,
       lpTokenBump: pool.lpTokenBump,
+      version: 1,
+      padding: ["0", "0", "0", "0", "0", "0", "0", "0"],
     };
     expect(JSON.stringify(pool)).to.equal(JSON.stringify(poolExpected));
   });
 
+  it("removePool", async () => {
+    let pool = await tc.findProgramAddress("pool", "temp pool");
+    let lpToken = await tc.findProgramAddress("lp_token_mint", [
+      pool.publicKey,
+    ]);
+    await tc.addPool("temp pool", new BN(100000000000), pool, lpToken);
+
+    let perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.pools.length).to.equal(2);
+
+    await tc.removePool(pool, lpToken);
+
+    // verify
+    perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.pools.length).to.equal(1);
+    expect(perpetuals.pools[0].toBase58()).to.equal(
+      tc.pool.publicKey.toBase58()
+    );
+    expect(await tc.program.account.pool.fetchNullable(pool.publicKey)).to.be
+      .null;
+  });
+
   it("addToken", async () => {
     // add
-    let oracleConfig = {
+    oracleConfig = {
       maxPriceError: new BN(10000),
       maxPriceAgeSec: 60,
       oracleType: { test: {} },
       oracleAccount: tc.custodies[0].oracleAccount,
     };
-    let pricingConfig = {
-      minInitialLeverage: new BN(1),
-      maxLeverage: new BN(100),
+    pricingConfig = {
+      minInitialLeverage: new BN(10000),
+      maxLeverage: new BN(1000000),
+      maxUtilization: new BN(8000),
+      maxLongOpenInterestUsd: new BN(1000000000),
+      maxShortOpenInterestUsd: new BN(1000000000),
+      minPositionSizeUsd: new BN(10000),
+      maxPositionSizeUsd: new BN(1000000000),
+      minCollateralUsd: new BN(10000),
+    };
+    feesConfig = {
+      openPosition: new BN(0),
+      closePosition: new BN(0),
+      liquidation: new BN(100),
+      insuranceShare: new BN(5000),
     };
-    await tc.addToken(tc.custodies[0], oracleConfig, pricingConfig);
+    await tc.addToken(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig,
+      feesConfig,
+      tc.toTokenAmount(1000, tc.custodies[0].decimals)
+    );
 
     let oracleConfig2 = Object.assign({}, oracleConfig);
     oracleConfig2.oracleAccount = tc.custodies[1].oracleAccount;
-    await tc.addToken(tc.custodies[1], oracleConfig2, pricingConfig);
+    await tc.addToken(
+      tc.custodies[1],
+      true,
+      oracleConfig2,
+      pricingConfig,
+      feesConfig,
+      tc.toTokenAmount(1000, tc.custodies[1].decimals)
+    );
 
     // verify
     let custody = await tc.program.account.custody.fetch(
@@ -68,6 +181,7 @@ describe("perpsDemo", () => {
       tokenAccount: tc.custodies[0].tokenAccount,
       mint: tc.custodies[0].mint.publicKey,
       decimals: 9,
+      isStable: false,
       oracle: {
         oracleAccount: tc.custodies[0].oracleAccount,
         oracleType: { test: {} },
@@ -75,16 +189,300 @@ describe("perpsDemo", () => {
         maxPriceAgeSec: 60,
       },
       pricing: {
-        minInitialLeverage: "1",
-        maxLeverage: "100",
+        minInitialLeverage: "10000",
+        maxLeverage: "1000000",
+        maxUtilization: "8000",
+        maxLongOpenInterestUsd: "1000000000",
+        maxShortOpenInterestUsd: "1000000000",
+        minPositionSizeUsd: "10000",
+        maxPositionSizeUsd: "1000000000",
+        minCollateralUsd: "10000",
+      },
+      fees: {
+        openPosition: "0",
+        closePosition: "0",
+        liquidation: "100",
+        insuranceShare: "5000",
       },
       assets: { collateral: "0", owned: "0", locked: "0" },
+      maxAmount: "1000000000000",
+      openInterest: {
+        longUsd: "0",
+        shortUsd: "0",
+        longTokens: "0",
+        shortTokens: "0",
+        longPositions: "0",
+        shortPositions: "0",
+      },
+      badDebt: "0",
+      permissions: {
+        allowSwap: true,
+        allowAddLiquidity: true,
+        allowRemoveLiquidity: true,
+        allowOpenPosition: true,
+        allowClosePosition: true,
+        allowLiquidation: true,
+      },
       bump: custody.bump,
       tokenAccountBump: custody.tokenAccountBump,
+      insuranceFundBump: custody.insuranceFundBump,
+      version: 1,
+      padding: ["0", "0", "0", "0", "0", "0", "0", "0"],
     };
     expect(JSON.stringify(custody)).to.equal(JSON.stringify(custodyExpected));
   });
 
+  it("setDepositCaps", async () => {
+    await tc.setDepositCaps(
+      new BN(200000000000),
+      tc.toTokenAmount(2000, tc.custodies[0].decimals),
+      tc.custodies[0]
+    );
+    let pendingChange = await tc.program.account.pendingChange.fetch(
+      await tc.getPendingChange(tc.custodies[0], true)
+    );
+    expect(JSON.stringify(pendingChange.changeType)).to.equal(
+      JSON.stringify({ depositCaps: {} })
+    );
+    expect(pendingChange.depositCaps.maxAumUsd.toString()).to.equal(
+      "200000000000"
+    );
+    await tc.applyPendingChange(tc.custodies[0], true);
+
+    // verify
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(pool.maxAumUsd.toString()).to.equal("200000000000");
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    custodyExpected.maxAmount = "2000000000000";
+    expect(JSON.stringify(custody)).to.equal(JSON.stringify(custodyExpected));
+  });
+
+  it("setCustodyConfig", async () => {
+    let pricingConfig2 = Object.assign({}, pricingConfig);
+    pricingConfig2.maxLeverage = new BN(2000000);
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig2,
+      feesConfig
+    );
+    await tc.applyPendingChange(tc.custodies[0]);
+
+    // verify
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.pricing.maxLeverage.toString()).to.equal("2000000");
+
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig,
+      feesConfig
+    );
+    await tc.applyPendingChange(tc.custodies[0]);
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(JSON.stringify(custody)).to.equal(JSON.stringify(custodyExpected));
+
+    // existing custody can't be re-added
+    let failed = false;
+    try {
+      await tc.addToken(
+        tc.custodies[0],
+        false,
+        oracleConfig,
+        pricingConfig,
+        feesConfig,
+        tc.toTokenAmount(1000, tc.custodies[0].decimals)
+      );
+    } catch (err) {
+      failed = true;
+    }
+    expect(failed).to.be.true;
+  });
+
+  it("timelock", async () => {
+    await tc.setTimelockDelay(new BN(3));
+    let pricingConfig2 = Object.assign({}, pricingConfig);
+    pricingConfig2.maxLeverage = new BN(2000000);
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig2,
+      feesConfig
+    );
+
+    // can't be applied before the delay has passed
+    let failed = false;
+    try {
+      await tc.applyPendingChange(tc.custodies[0]);
+    } catch (err) {
+      failed = true;
+    }
+    expect(failed).to.be.true;
+
+    await tc.cancelPendingChange(tc.custodies[0]);
+
+    // deposit caps are queued behind the same delay
+    await tc.setDepositCaps(
+      new BN(200000000000),
+      tc.toTokenAmount(2000, tc.custodies[0].decimals),
+      tc.custodies[0]
+    );
+    await expectError(
+      tc.applyPendingChange(tc.custodies[0], true),
+      "TimelockNotExpired"
+    );
+    await tc.cancelPendingChange(tc.custodies[0], true);
+
+    // reductions are queued behind the current delay
+    await tc.setTimelockDelay(new BN(0));
+    let perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.timelockDelaySec.toString()).to.equal("3");
+    expect(perpetuals.pendingTimelockDelaySec.toString()).to.equal("0");
+    expect(perpetuals.pendingTimelockDelayTime.toNumber()).to.be.greaterThan(0);
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await tc.setTimelockDelay(new BN(0));
+    perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.timelockDelaySec.toString()).to.equal("0");
+    expect(perpetuals.pendingTimelockDelayTime.toString()).to.equal("0");
+
+    // verify
+    let pendingChange = await tc.program.account.pendingChange.fetchNullable(
+      await tc.getPendingChange(tc.custodies[0])
+    );
+    expect(pendingChange).to.be.null;
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(JSON.stringify(custody)).to.equal(JSON.stringify(custodyExpected));
+  });
+
+  it("removeToken", async () => {
+    let custody = await tc.generateCustody(9);
+    await spl.createMint(
+      tc.provider.connection,
+      tc.admin,
+      tc.admin.publicKey,
+      null,
+      custody.decimals,
+      custody.mint
+    );
+    let oracleConfig2 = Object.assign({}, oracleConfig);
+    oracleConfig2.oracleType = { none: {} };
+    await tc.addToken(
+      custody,
+      false,
+      oracleConfig2,
+      pricingConfig,
+      feesConfig,
+      tc.toTokenAmount(1000, custody.decimals)
+    );
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(pool.tokens.length).to.equal(3);
+
+    // custody can't be removed while its insurance fund holds tokens
+    let adminTokenAccount = await spl.createAssociatedTokenAccount(
+      tc.provider.connection,
+      tc.admin,
+      custody.mint.publicKey,
+      tc.admin.publicKey
+    );
+    await tc.mintTokens(
+      1,
+      custody.decimals,
+      custody.mint.publicKey,
+      adminTokenAccount
+    );
+    await tc.topUpInsuranceFund(
+      tc.toTokenAmount(1, custody.decimals),
+      adminTokenAccount,
+      custody
+    );
+    await expectError(tc.removeToken(custody), "CustodyNotEmpty");
+    await tc.withdrawInsuranceFund(
+      tc.toTokenAmount(1, custody.decimals),
+      adminTokenAccount,
+      custody
+    );
+
+    await tc.removeToken(custody);
+
+    // verify
+    pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(pool.tokens.length).to.equal(2);
+    // no stale bytes are left past the shrunk pool data
+    let poolInfo = await tc.provider.connection.getAccountInfo(
+      tc.pool.publicKey
+    );
+    let poolData = await tc.program.coder.accounts.encode("Pool", pool);
+    expect(poolInfo.data.subarray(poolData.length).every((b) => b == 0)).to.be
+      .true;
+    expect(
+      await tc.program.account.custody.fetchNullable(custody.custody)
+    ).to.be.null;
+    expect(
+      await tc.provider.connection.getAccountInfo(custody.tokenAccount)
+    ).to.be.null;
+    expect(
+      await tc.provider.connection.getAccountInfo(
+        custody.insuranceFundTokenAccount
+      )
+    ).to.be.null;
+  });
+
+  it("insuranceFund", async () => {
+    let adminTokenAccount = await spl.createAssociatedTokenAccount(
+      tc.provider.connection,
+      tc.admin,
+      tc.custodies[0].mint.publicKey,
+      tc.admin.publicKey
+    );
+    await tc.mintTokens(
+      10,
+      tc.custodies[0].decimals,
+      tc.custodies[0].mint.publicKey,
+      adminTokenAccount
+    );
+    await tc.topUpInsuranceFund(
+      tc.toTokenAmount(10, tc.custodies[0].decimals),
+      adminTokenAccount,
+      tc.custodies[0]
+    );
+
+    // withdrawals need the multisig, role holders can't drain the fund
+    await expectError(
+      tc.withdrawInsuranceFund(
+        tc.toTokenAmount(4, tc.custodies[0].decimals),
+        adminTokenAccount,
+        tc.custodies[0],
+        Keypair.generate()
+      ),
+      "MultisigAccountNotAuthorized"
+    );
+    await tc.withdrawInsuranceFund(
+      tc.toTokenAmount(4, tc.custodies[0].decimals),
+      adminTokenAccount,
+      tc.custodies[0]
+    );
+
+    // verify
+    let balance = await tc.getBalance(
+      tc.custodies[0].insuranceFundTokenAccount
+    );
+    expect(balance).to.equal(6000000000);
+  });
+
   it("setTestOraclePrice", async () => {
     // set
     await tc.setTestOraclePrice(tc.toTokenAmount(1.23, 3), tc.custodies[0]);
@@ -156,6 +554,83 @@ describe("perpsDemo", () => {
     expect(balance1 - initialBalance1).to.equal(3075000);
   });
 
+  it("deadline", async () => {
+    let initialBalance0 = await tc.getBalance(tc.user.tokenAccounts[0]);
+
+    await expectError(
+      tc.swap(
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        tc.toTokenAmount(0.1, tc.custodies[1].decimals),
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.tokenAccounts[1],
+        tc.custodies[0],
+        tc.custodies[1],
+        new BN(tc.getTime() - 3600)
+      ),
+      "DeadlineExceeded"
+    );
+
+    // verify nothing was swapped
+    let balance0 = await tc.getBalance(tc.user.tokenAccounts[0]);
+    expect(balance0).to.equal(initialBalance0);
+  });
+
+  it("setPermissions", async () => {
+    let permissions = Object.assign({}, custodyExpected.permissions);
+    permissions.allowSwap = false;
+    await tc.setPermissions(permissions, tc.custodies[0].custody);
+
+    // verify
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.permissions.allowSwap).to.be.false;
+
+    let failed = false;
+    try {
+      await tc.swap(
+        tc.toTokenAmount(5, tc.custodies[0].decimals),
+        tc.toTokenAmount(1, tc.custodies[1].decimals),
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.tokenAccounts[1],
+        tc.custodies[0],
+        tc.custodies[1]
+      );
+    } catch (err) {
+      failed = true;
+    }
+    expect(failed).to.be.true;
+
+    await tc.setPermissions(
+      custodyExpected.permissions,
+      tc.custodies[0].custody
+    );
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(JSON.stringify(custody)).to.equal(JSON.stringify(custodyExpected));
+
+    // pauser can revoke permissions on its own but can't grant them
+    let pauser = Keypair.generate();
+    await tc.setRole({ pauser: {} }, pauser.publicKey);
+    await tc.setPermissions(permissions, tc.custodies[0].custody, pauser);
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(custody.permissions.allowSwap).to.be.false;
+    await expectError(
+      tc.setPermissions(
+        custodyExpected.permissions,
+        tc.custodies[0].custody,
+        pauser
+      ),
+      "MultisigAccountNotAuthorized"
+    );
+    await tc.setPermissions(
+      custodyExpected.permissions,
+      tc.custodies[0].custody
+    );
+    await tc.setRole({ pauser: {} }, tc.admin.publicKey);
+  });
+
   it("removeLiquidity", async () => {
     // remove
     await tc.removeLiquidity(
@@ -182,35 +657,1196 @@ describe("perpsDemo", () => {
 
   it("openPosition", async () => {
     await tc.openPosition(
-      tc.toTokenAmount(1.23, 3),
+      tc.toTokenAmount(1.23, 6),
       tc.toTokenAmount(1, tc.custodies[0].decimals),
-      tc.toTokenAmount(10, tc.custodies[0].decimals),
+      tc.toTokenAmount(5, tc.custodies[0].decimals),
       "long",
       tc.user,
       tc.user.tokenAccounts[0],
       tc.user.positionAccountsLong[0],
+      tc.custodies[0],
       tc.custodies[0]
     );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.collateralCustody.toBase58()).to.equal(
+      tc.custodies[0].custody.toBase58()
+    );
+    expect(position.sizeUsd.toString()).to.equal("6150000");
+    expect(position.collateralUsd.toString()).to.equal("1230000");
+
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.openInterest.longUsd.toString()).to.equal("6150000");
   });
 
-  it("closePosition", async () => {
-    await tc.closePosition(
-      tc.toTokenAmount(1.23, 3),
-      tc.toTokenAmount(5, tc.custodies[0].decimals),
+  it("removeLiquidityPartial", async () => {
+    await tc.setTestOraclePrice(tc.toTokenAmount(1.23, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(2, 3), tc.custodies[1]);
+
+    // most of custody funds are locked by the open position
+    let lpAmount = tc.toTokenAmount(10, 6);
+    await expectError(
+      tc.removeLiquidity(
+        lpAmount,
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.custodies[0]
+      ),
+      "InsufficientLiquidity"
+    );
+
+    let initialLpBalance = await tc.getBalance(tc.user.lpTokenAccount);
+    let initialBalance = await tc.getBalance(tc.user.tokenAccounts[0]);
+    await tc.removeLiquidity(
+      lpAmount,
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.custodies[0],
+      true
+    );
+
+    // verify only unlocked funds are removed and lp tokens are burned pro rata
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.assets.owned.toString()).to.equal(
+      custody.assets.locked.toString()
+    );
+    let burned =
+      initialLpBalance - (await tc.getBalance(tc.user.lpTokenAccount));
+    expect(burned).to.be.greaterThan(0);
+    expect(burned).to.be.lessThan(lpAmount.toNumber());
+
+    // restore pool liquidity
+    let removed =
+      (await tc.getBalance(tc.user.tokenAccounts[0])) - initialBalance;
+    await tc.addLiquidity(
+      new BN(removed),
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.custodies[0]
+    );
+  });
+
+  it("addCollateral", async () => {
+    await tc.addCollateral(
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
       tc.user,
       tc.user.tokenAccounts[0],
       tc.user.positionAccountsLong[0],
+      tc.custodies[0],
       tc.custodies[0]
     );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.sizeUsd.toString()).to.equal("6150000");
+    expect(position.collateralUsd.toString()).to.equal("2460000");
   });
 
-  it("liquidate", async () => {
-    await tc.liquidate(
+  it("removeCollateral", async () => {
+    // remaining collateral must keep the position within max leverage
+    await expectError(
+      tc.removeCollateral(
+        tc.toTokenAmount(2.43, 6),
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0]
+      ),
+      "MaxLeverage"
+    );
+
+    await tc.removeCollateral(
+      tc.toTokenAmount(1.23, 6),
       tc.user,
       tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.sizeUsd.toString()).to.equal("6150000");
+    expect(position.collateralUsd.toString()).to.equal("1230000");
+  });
+
+  it("closePosition", async () => {
+    await tc.closePosition(
+      tc.toTokenAmount(1.23, 6),
+      tc.toTokenAmount(3.075, 6),
+      tc.user,
       tc.user.tokenAccounts[0],
       tc.user.positionAccountsLong[0],
+      tc.custodies[0],
       tc.custodies[0]
     );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.sizeUsd.toString()).to.equal("3075000");
+    expect(position.collateralUsd.toString()).to.equal("615000");
+  });
+
+  it("shortPosition", async () => {
+    await tc.setTestOraclePrice(tc.toTokenAmount(1.23, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(2.0, 3), tc.custodies[1]);
+    let initialCustody = await tc.program.account.custody.fetch(
+      tc.custodies[1].custody
+    );
+
+    // stable tokens can't be shorted against themselves
+    await expectError(
+      tc.openPosition(
+        tc.toTokenAmount(2, 6),
+        tc.toTokenAmount(2, tc.custodies[1].decimals),
+        tc.toTokenAmount(1, tc.custodies[1].decimals),
+        "short",
+        tc.user,
+        tc.user.tokenAccounts[1],
+        tc.user.positionAccountsShort[1],
+        tc.custodies[1],
+        tc.custodies[1]
+      ),
+      "InvalidCollateralCustody"
+    );
+
+    await tc.openPosition(
+      tc.toTokenAmount(1.23, 6),
+      tc.toTokenAmount(2, tc.custodies[1].decimals),
+      tc.toTokenAmount(5, tc.custodies[0].decimals),
+      "short",
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+
+    // verify collateral and locked funds are held by the stable custody
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsShort[0]
+    );
+    expect(position.collateralCustody.toBase58()).to.equal(
+      tc.custodies[1].custody.toBase58()
+    );
+    expect(position.sizeUsd.toString()).to.equal("6150000");
+    expect(position.collateralUsd.toString()).to.equal("4000000");
+    expect(position.lockedAmount.toString()).to.equal("3075000");
+
+    let collateralCustody = await tc.program.account.custody.fetch(
+      tc.custodies[1].custody
+    );
+    expect(collateralCustody.assets.locked.toString()).to.equal(
+      initialCustody.assets.locked.addn(3075000).toString()
+    );
+    expect(collateralCustody.assets.collateral.toString()).to.equal(
+      initialCustody.assets.collateral.addn(2000000).toString()
+    );
+    expect(collateralCustody.assets.owned.toString()).to.equal(
+      initialCustody.assets.owned.toString()
+    );
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.openInterest.shortUsd.toString()).to.equal("6150000");
+
+    await tc.closePosition(
+      tc.toTokenAmount(1.23, 6),
+      new BN(0),
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+
+    // verify locked funds are released and collateral is returned
+    expect(
+      await tc.program.account.position.fetchNullable(
+        tc.user.positionAccountsShort[0]
+      )
+    ).to.be.null;
+    collateralCustody = await tc.program.account.custody.fetch(
+      tc.custodies[1].custody
+    );
+    expect(JSON.stringify(collateralCustody.assets)).to.equal(
+      JSON.stringify(initialCustody.assets)
+    );
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(custody.openInterest.shortUsd.toString()).to.equal("0");
+  });
+
+  it("increasePosition", async () => {
+    await tc.openPosition(
+      tc.toTokenAmount(1.23, 6),
+      tc.toTokenAmount(1, tc.custodies[1].decimals),
+      tc.toTokenAmount(2, tc.custodies[0].decimals),
+      "short",
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    await tc.setTestOraclePrice(tc.toTokenAmount(1.5, 3), tc.custodies[0]);
+    await tc.openPosition(
+      tc.toTokenAmount(1.5, 6),
+      tc.toTokenAmount(1, tc.custodies[1].decimals),
+      tc.toTokenAmount(2, tc.custodies[0].decimals),
+      "short",
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+
+    // verify entry price is weighted by size
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsShort[0]
+    );
+    expect(position.price.toString()).to.equal("1378351");
+    expect(position.sizeUsd.toString()).to.equal("5460000");
+    expect(position.collateralUsd.toString()).to.equal("4000000");
+
+    // existing position can't be increased with another collateral token
+    let stableCustody = await tc.generateCustody(6);
+    await spl.createMint(
+      tc.provider.connection,
+      tc.admin,
+      tc.admin.publicKey,
+      null,
+      stableCustody.decimals,
+      stableCustody.mint
+    );
+    let oracleConfig2 = Object.assign({}, oracleConfig);
+    oracleConfig2.oracleAccount = stableCustody.oracleAccount;
+    await tc.addToken(
+      stableCustody,
+      true,
+      oracleConfig2,
+      pricingConfig,
+      feesConfig,
+      tc.toTokenAmount(1000, stableCustody.decimals)
+    );
+    await tc.setTestOraclePrice(tc.toTokenAmount(1, 3), stableCustody);
+    let fundingAccount = await spl.createAssociatedTokenAccount(
+      tc.provider.connection,
+      tc.admin,
+      stableCustody.mint.publicKey,
+      tc.user.wallet.publicKey
+    );
+    await tc.mintTokens(
+      10,
+      stableCustody.decimals,
+      stableCustody.mint.publicKey,
+      fundingAccount
+    );
+    await expectError(
+      tc.openPosition(
+        tc.toTokenAmount(1.5, 6),
+        tc.toTokenAmount(1, stableCustody.decimals),
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        "short",
+        tc.user,
+        fundingAccount,
+        tc.user.positionAccountsShort[0],
+        tc.custodies[0],
+        stableCustody
+      ),
+      "InvalidCollateralCustody"
+    );
+    await tc.removeToken(stableCustody);
+
+    await tc.closePosition(
+      tc.toTokenAmount(1.5, 6),
+      new BN(0),
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    expect(
+      await tc.program.account.position.fetchNullable(
+        tc.user.positionAccountsShort[0]
+      )
+    ).to.be.null;
+  });
+
+  it("positionLimits", async () => {
+    await tc.setTestOraclePrice(tc.toTokenAmount(1.5, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(2, 3), tc.custodies[1]);
+    let openShort = (collateral: number, size: number) =>
+      tc.openPosition(
+        tc.toTokenAmount(1.5, 6),
+        tc.toTokenAmount(collateral, tc.custodies[1].decimals),
+        tc.toTokenAmount(size, tc.custodies[0].decimals),
+        "short",
+        tc.user,
+        tc.user.tokenAccounts[1],
+        tc.user.positionAccountsShort[0],
+        tc.custodies[0],
+        tc.custodies[1]
+      );
+
+    await expectError(openShort(0.002, 0.005), "MinPositionSize");
+    await expectError(openShort(100, 1000), "MaxPositionSize");
+    await expectError(openShort(0.004, 0.4), "MinCollateral");
+
+    // partial close that would leave dust behind closes the whole position
+    await openShort(1, 2);
+    await tc.closePosition(
+      tc.toTokenAmount(1.5, 6),
+      tc.toTokenAmount(2.995, 6),
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    expect(
+      await tc.program.account.position.fetchNullable(
+        tc.user.positionAccountsShort[0]
+      )
+    ).to.be.null;
+  });
+
+  it("maxOpenInterest", async () => {
+    let pricingConfig2 = Object.assign({}, pricingConfig);
+    pricingConfig2.maxLongOpenInterestUsd = new BN(4000000);
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig2,
+      feesConfig
+    );
+    await tc.applyPendingChange(tc.custodies[0]);
+
+    // long open interest is 3.075 USD, another 1.5 USD exceeds the cap
+    await expectError(
+      tc.openPosition(
+        tc.toTokenAmount(1.5, 6),
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        "long",
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0]
+      ),
+      "MaxOpenInterest"
+    );
+
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig,
+      feesConfig
+    );
+    await tc.applyPendingChange(tc.custodies[0]);
+  });
+
+  it("maxUtilization", async () => {
+    // locking 10 more tokens exceeds 80% of owned funds
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(
+      custody.assets.locked
+        .add(tc.toTokenAmount(10, tc.custodies[0].decimals))
+        .muln(10000)
+        .gt(custody.assets.owned.muln(8000))
+    ).to.be.true;
+    await expectError(
+      tc.openPosition(
+        tc.toTokenAmount(1.5, 6),
+        tc.toTokenAmount(2, tc.custodies[0].decimals),
+        tc.toTokenAmount(10, tc.custodies[0].decimals),
+        "long",
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0]
+      ),
+      "MaxUtilization"
+    );
+  });
+
+  it("liquidate", async () => {
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.5, 3), tc.custodies[0]);
+    await tc.liquidate(
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+
+    // verify shortfall is covered by the insurance fund
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.badDebt.toString()).to.equal("0");
+    let balance = await tc.getBalance(
+      tc.custodies[0].insuranceFundTokenAccount
+    );
+    expect(balance).to.equal(3580000000);
+  });
+
+  it("badDebt", async () => {
+    await tc.openPosition(
+      tc.toTokenAmount(0.5, 6),
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      tc.toTokenAmount(5, tc.custodies[0].decimals),
+      "long",
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+    let initialCustody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    let initialInsuranceBalance = await tc.getBalance(
+      tc.custodies[0].insuranceFundTokenAccount
+    );
+
+    let listener;
+    let badDebtEvent = new Promise<any>((resolve) => {
+      listener = tc.program.addEventListener("BadDebtEvent", (event) =>
+        resolve(event)
+      );
+    });
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.3, 3), tc.custodies[0]);
+    await tc.liquidate(
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+    let event = await badDebtEvent;
+    await tc.program.removeEventListener(listener);
+
+    // verify the shortfall is recorded as profit LPs forgo
+    expect(event.badDebtAmount.gtn(0)).to.be.true;
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.badDebt.toString()).to.equal(
+      initialCustody.badDebt.add(event.badDebtAmount).toString()
+    );
+    expect(custody.assets.owned.toString()).to.equal(
+      initialCustody.assets.owned
+        .add(tc.toTokenAmount(1, tc.custodies[0].decimals))
+        .toString()
+    );
+    expect(
+      await tc.getBalance(tc.custodies[0].insuranceFundTokenAccount)
+    ).to.equal(initialInsuranceBalance);
+
+    // verify no tokens are stranded in the custody
+    let balance = await tc.getBalance(tc.custodies[0].tokenAccount);
+    expect(balance.toString()).to.equal(
+      custody.assets.owned.add(custody.assets.collateral).toString()
+    );
+
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.5, 3), tc.custodies[0]);
+  });
+
+  it("executeOrder", async () => {
+    // orders are executed by a keeper that is paid the execution fee
+    let wallet = Keypair.generate();
+    await tc.confirmTx(await tc.requestAirdrop(wallet.publicKey));
+    keeper = {
+      wallet,
+      tokenAccount: await spl.createAssociatedTokenAccount(
+        tc.provider.connection,
+        tc.admin,
+        tc.custodies[0].mint.publicKey,
+        wallet.publicKey
+      ),
+    };
+    let executionFee = tc.toTokenAmount(0.01, tc.custodies[0].decimals);
+
+    // several orders can rest on the same custody and side
+    let initialBalance = await tc.getBalance(tc.user.tokenAccounts[0]);
+    for (let [orderId, triggerPrice] of [
+      [0, 0.6],
+      [1, 0.4],
+    ]) {
+      await tc.createOrder(
+        tc.toTokenAmount(triggerPrice, 6),
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        tc.toTokenAmount(2, tc.custodies[0].decimals),
+        "long",
+        executionFee,
+        new BN(tc.getTime() + 3600),
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.custodies[0],
+        tc.custodies[0],
+        orderId
+      );
+    }
+    await expectError(
+      tc.executeOrder(
+        keeper,
+        keeper.tokenAccount,
+        tc.user,
+        "long",
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0],
+        1
+      ),
+      "OrderNotTriggered"
+    );
+    await tc.executeOrder(
+      keeper,
+      keeper.tokenAccount,
+      tc.user,
+      "long",
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0],
+      0
+    );
+    await tc.cancelOrder(
+      tc.user,
+      tc.user.tokenAccounts[0],
+      "long",
+      tc.custodies[0],
+      1
+    );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.sizeUsd.toString()).to.equal("1000000");
+    expect(position.collateralUsd.toString()).to.equal("500000");
+    let balance = await tc.getBalance(tc.user.tokenAccounts[0]);
+    expect(initialBalance - balance).to.equal(
+      1000000000 + executionFee.toNumber()
+    );
+    expect(await tc.getBalance(keeper.tokenAccount)).to.equal(
+      executionFee.toNumber()
+    );
+
+    // expired orders can't be executed
+    let expirationTime = (await tc.getChainTime()) + 3;
+    await tc.createOrder(
+      tc.toTokenAmount(0.6, 6),
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      tc.toTokenAmount(2, tc.custodies[0].decimals),
+      "long",
+      executionFee,
+      new BN(expirationTime),
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.custodies[0],
+      tc.custodies[0],
+      2
+    );
+    while ((await tc.getChainTime()) <= expirationTime) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    await expectError(
+      tc.executeOrder(
+        keeper,
+        keeper.tokenAccount,
+        tc.user,
+        "long",
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0],
+        2
+      ),
+      "OrderExpired"
+    );
+    await tc.cancelOrder(
+      tc.user,
+      tc.user.tokenAccounts[0],
+      "long",
+      tc.custodies[0],
+      2
+    );
+  });
+
+  it("cancelOrder", async () => {
+    let initialBalance = await tc.getBalance(tc.user.tokenAccounts[0]);
+    await tc.createOrder(
+      tc.toTokenAmount(0.4, 6),
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      tc.toTokenAmount(2, tc.custodies[0].decimals),
+      "long",
+      tc.toTokenAmount(0.01, tc.custodies[0].decimals),
+      new BN(tc.getTime() + 3600),
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+    await tc.cancelOrder(
+      tc.user,
+      tc.user.tokenAccounts[0],
+      "long",
+      tc.custodies[0]
+    );
+
+    // verify
+    let balance = await tc.getBalance(tc.user.tokenAccounts[0]);
+    expect(balance).to.equal(initialBalance);
+  });
+
+  it("executeTrigger", async () => {
+    await tc.setTrigger(
+      tc.toTokenAmount(0.4, 6),
+      tc.toTokenAmount(0.55, 6),
+      tc.toTokenAmount(0.5, 6),
+      tc.toTokenAmount(0.01, 6),
+      tc.user,
+      tc.user.positionAccountsLong[0]
+    );
+    await expectError(
+      tc.executeTrigger(
+        keeper,
+        keeper.tokenAccount,
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0]
+      ),
+      "PositionNotTriggered"
+    );
+
+    // partial close, the executor is paid the fee in collateral tokens
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.6, 3), tc.custodies[0]);
+    let initialKeeperBalance = await tc.getBalance(keeper.tokenAccount);
+    await tc.executeTrigger(
+      keeper,
+      keeper.tokenAccount,
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+
+    // verify
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position.sizeUsd.toString()).to.equal("500000");
+    expect(position.stopLossPrice.toString()).to.equal("0");
+    expect(position.takeProfitPrice.toString()).to.equal("0");
+    expect(position.triggerSizeUsd.toString()).to.equal("0");
+    expect(position.triggerFeeUsd.toString()).to.equal("0");
+    // 0.01 USD at 0.6 USD per token
+    expect(
+      (await tc.getBalance(keeper.tokenAccount)) - initialKeeperBalance
+    ).to.equal(16666666);
+
+    // triggers fire once, the remaining size is not closed again
+    await expectError(
+      tc.executeTrigger(
+        keeper,
+        keeper.tokenAccount,
+        tc.user,
+        tc.user.tokenAccounts[0],
+        tc.user.positionAccountsLong[0],
+        tc.custodies[0],
+        tc.custodies[0]
+      ),
+      "PositionNotTriggered"
+    );
+
+    // full close once a new trigger is set
+    await tc.setTrigger(
+      tc.toTokenAmount(0.4, 6),
+      tc.toTokenAmount(0.55, 6),
+      new BN(0),
+      tc.toTokenAmount(0.01, 6),
+      tc.user,
+      tc.user.positionAccountsLong[0]
+    );
+    await tc.executeTrigger(
+      keeper,
+      keeper.tokenAccount,
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+    position = await tc.program.account.position.fetchNullable(
+      tc.user.positionAccountsLong[0]
+    );
+    expect(position).to.be.null;
+  });
+
+  it("getAdlQueue", async () => {
+    await tc.openPosition(
+      tc.toTokenAmount(0.7, 6),
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      tc.toTokenAmount(5, tc.custodies[0].decimals),
+      "long",
+      tc.user,
+      tc.user.tokenAccounts[0],
+      tc.user.positionAccountsLong[0],
+      tc.custodies[0],
+      tc.custodies[0]
+    );
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.7, 3), tc.custodies[0]);
+
+    // verify
+    let queue = await tc.getAdlQueue(tc.custodies[0], tc.custodies[0], "long", [
+      tc.user.positionAccountsLong[0],
+    ]);
+    expect(queue.positions.length).to.equal(1);
+    expect(queue.positions[0].position.toBase58()).to.equal(
+      tc.user.positionAccountsLong[0].toBase58()
+    );
+    expect(queue.liabilities.lt(queue.assets)).to.be.true;
+  });
+
+  it("autoDeleverage", async () => {
+    await tc.setTestOraclePrice(tc.toTokenAmount(1, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(2, 3), tc.custodies[1]);
+
+    // second trader with a smaller short
+    let wallet = Keypair.generate();
+    await tc.confirmTx(await tc.requestAirdrop(wallet.publicKey));
+    let tokenAccount = await spl.createAssociatedTokenAccount(
+      tc.provider.connection,
+      tc.admin,
+      tc.custodies[1].mint.publicKey,
+      wallet.publicKey
+    );
+    await tc.mintTokens(
+      10,
+      tc.custodies[1].decimals,
+      tc.custodies[1].mint.publicKey,
+      tokenAccount
+    );
+    let positionAccount = (
+      await tc.findProgramAddress("position", [
+        wallet.publicKey,
+        tc.pool.publicKey,
+        tc.custodies[0].custody,
+        [2],
+      ])
+    ).publicKey;
+    let user2 = { wallet };
+
+    await tc.openPosition(
+      tc.toTokenAmount(1, 6),
+      tc.toTokenAmount(0.5, tc.custodies[1].decimals),
+      tc.toTokenAmount(2, tc.custodies[0].decimals),
+      "short",
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    await tc.openPosition(
+      tc.toTokenAmount(1, 6),
+      tc.toTokenAmount(0.25, tc.custodies[1].decimals),
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      "short",
+      user2,
+      tokenAccount,
+      positionAccount,
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.openInterest.shortUsd.toString()).to.equal("3000000");
+    expect(custody.openInterest.shortTokens.toString()).to.equal("3000000");
+    expect(custody.openInterest.shortPositions.toString()).to.equal("2");
+
+    // pool is solvent
+    let queue = [positionAccount, tc.user.positionAccountsShort[0]];
+    await expectError(
+      tc.autoDeleverage(
+        tc.user,
+        tc.user.tokenAccounts[1],
+        tc.user.positionAccountsShort[0],
+        tc.custodies[0],
+        tc.custodies[1],
+        queue
+      ),
+      "AutoDeleverageNotRequired"
+    );
+
+    // stable collateral depegs while shorts are in profit, so unrealized
+    // profit exceeds what the collateral custody owns
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.5, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(0.2, 3), tc.custodies[1]);
+
+    let adlQueue = await tc.getAdlQueue(
+      tc.custodies[0],
+      tc.custodies[1],
+      "short",
+      queue
+    );
+    expect(adlQueue.liabilities.gt(adlQueue.assets)).to.be.true;
+    expect(adlQueue.positions.length).to.equal(2);
+    expect(adlQueue.positions[0].position.toBase58()).to.equal(
+      tc.user.positionAccountsShort[0].toBase58()
+    );
+    expect(adlQueue.positions[0].profitUsd.toString()).to.equal("1000000");
+    expect(adlQueue.positions[1].position.toBase58()).to.equal(
+      positionAccount.toBase58()
+    );
+    expect(adlQueue.positions[1].profitUsd.toString()).to.equal("500000");
+
+    // target must rank first among the supplied candidates
+    await expectError(
+      tc.autoDeleverage(
+        tc.user,
+        tc.user.tokenAccounts[1],
+        tc.user.positionAccountsShort[0],
+        tc.custodies[0],
+        tc.custodies[1],
+        [positionAccount]
+      ),
+      "InvalidAdlPosition"
+    );
+
+    // only the most profitable position can be deleveraged
+    await expectError(
+      tc.autoDeleverage(
+        user2,
+        tokenAccount,
+        positionAccount,
+        tc.custodies[0],
+        tc.custodies[1],
+        queue
+      ),
+      "InvalidAdlPosition"
+    );
+
+    await tc.autoDeleverage(
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1],
+      queue
+    );
+
+    // verify the top position was partially closed and the shortfall shrank
+    let position = await tc.program.account.position.fetch(
+      tc.user.positionAccountsShort[0]
+    );
+    let adlQueueAfter = await tc.getAdlQueue(
+      tc.custodies[0],
+      tc.custodies[1],
+      "short",
+      queue
+    );
+    expect(
+      adlQueueAfter.liabilities
+        .sub(adlQueueAfter.assets)
+        .lt(adlQueue.liabilities.sub(adlQueue.assets))
+    ).to.be.true;
+    expect(position.sizeUsd.gtn(0)).to.be.true;
+    expect(position.sizeUsd.ltn(2000000)).to.be.true;
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(custody.openInterest.shortUsd.toString()).to.equal(
+      position.sizeUsd.addn(1000000).toString()
+    );
+
+    // close remaining positions at the entry price
+    await tc.setTestOraclePrice(tc.toTokenAmount(1, 3), tc.custodies[0]);
+    await tc.setTestOraclePrice(tc.toTokenAmount(2, 3), tc.custodies[1]);
+    await tc.closePosition(
+      tc.toTokenAmount(1, 6),
+      new BN(0),
+      tc.user,
+      tc.user.tokenAccounts[1],
+      tc.user.positionAccountsShort[0],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    await tc.closePosition(
+      tc.toTokenAmount(1, 6),
+      new BN(0),
+      user2,
+      tokenAccount,
+      positionAccount,
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+    custody = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    expect(custody.openInterest.shortUsd.toString()).to.equal("0");
+    expect(custody.openInterest.shortTokens.toString()).to.equal("0");
+    expect(custody.openInterest.shortPositions.toString()).to.equal("0");
+  });
+
+  it("setRole", async () => {
+    let parameterManager = Keypair.generate();
+    await tc.confirmTx(await tc.requestAirdrop(parameterManager.publicKey));
+    await tc.setRole({ parameterManager: {} }, parameterManager.publicKey);
+
+    // verify
+    let perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.roles.parameterManager.toBase58()).to.equal(
+      parameterManager.publicKey.toBase58()
+    );
+
+    await tc.setDepositCaps(
+      new BN(300000000000),
+      tc.toTokenAmount(3000, tc.custodies[0].decimals),
+      tc.custodies[0],
+      parameterManager
+    );
+    await tc.applyPendingChange(tc.custodies[0], true);
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(pool.maxAumUsd.toString()).to.equal("300000000000");
+
+    // revoke
+    await tc.setRole({ parameterManager: {} }, PublicKey.default);
+    let failed = false;
+    try {
+      await tc.setDepositCaps(
+        new BN(400000000000),
+        tc.toTokenAmount(4000, tc.custodies[0].decimals),
+        tc.custodies[0],
+        parameterManager
+      );
+    } catch (err) {
+      failed = true;
+    }
+    expect(failed).to.be.true;
+
+    // admin doesn't hold roles it wasn't granted
+    await expectError(
+      tc.setDepositCaps(
+        new BN(400000000000),
+        tc.toTokenAmount(4000, tc.custodies[0].decimals),
+        tc.custodies[0]
+      ),
+      "MissingRole"
+    );
+    await tc.setRole({ parameterManager: {} }, tc.admin.publicKey);
+  });
+
+  it("setAdminSigners", async () => {
+    let signer = Keypair.generate();
+    await tc.setAdminSigners([tc.admin.publicKey, signer.publicKey], 2);
+
+    let multisig = await tc.program.account.multisig.fetch(
+      tc.multisig.publicKey
+    );
+    expect(multisig.numSigners).to.equal(2);
+    expect(multisig.minSignatures).to.equal(2);
+
+    // pending change rent is returned to the signer that paid it
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig,
+      feesConfig
+    );
+    await tc.setCustodyConfig(
+      tc.custodies[0],
+      false,
+      oracleConfig,
+      pricingConfig,
+      feesConfig,
+      signer
+    );
+    let pendingChange = await tc.program.account.pendingChange.fetch(
+      await tc.getPendingChange(tc.custodies[0])
+    );
+    expect(pendingChange.payer.toBase58()).to.equal(
+      tc.admin.publicKey.toBase58()
+    );
+    await tc.applyPendingChange(tc.custodies[0]);
+
+    // first signature only records the approval
+    await tc.setAdminSigners([tc.admin.publicKey], 1);
+    multisig = await tc.program.account.multisig.fetch(tc.multisig.publicKey);
+    expect(multisig.numSigners).to.equal(2);
+    expect(multisig.numSigned).to.equal(1);
+
+    // second signature executes the instruction
+    await tc.setAdminSigners([tc.admin.publicKey], 1, signer);
+    multisig = await tc.program.account.multisig.fetch(tc.multisig.publicKey);
+    expect(multisig.numSigners).to.equal(1);
+    expect(multisig.minSignatures).to.equal(1);
+    expect(multisig.numSigned).to.equal(0);
+  });
+
+  it("getAuditLog", async () => {
+    let auditLog = await tc.program.account.auditLog.fetch(
+      tc.auditLog.publicKey
+    );
+    expect(auditLog.numEntries.toNumber()).to.be.greaterThan(10);
+
+    // verify
+    let page = await tc.getAuditLog(0, 3);
+    expect(page.numEntries.toString()).to.equal(
+      auditLog.numEntries.toString()
+    );
+    expect(page.entries.length).to.equal(3);
+    expect(JSON.stringify(page.entries[0].action)).to.equal(
+      JSON.stringify({ init: {} })
+    );
+    expect(JSON.stringify(page.entries[1].action)).to.equal(
+      JSON.stringify({ setRole: {} })
+    );
+    expect(page.entries[0].signer.toBase58()).to.equal(
+      tc.admin.publicKey.toBase58()
+    );
+
+    let lastPage = await tc.getAuditLog(
+      auditLog.numEntries.toNumber() - 1,
+      10
+    );
+    expect(lastPage.entries.length).to.equal(1);
+    expect(lastPage.entries[0].index.toString()).to.equal(
+      auditLog.numEntries.subn(1).toString()
+    );
+  });
+
+  it("acceptAdmin", async () => {
+    let newAdmin = Keypair.generate();
+    await tc.proposeAdmin(newAdmin.publicKey);
+    await tc.cancelAdminProposal();
+
+    let perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.pendingAdmin.toBase58()).to.equal(
+      PublicKey.default.toBase58()
+    );
+
+    await tc.proposeAdmin(newAdmin.publicKey);
+    await tc.acceptAdmin(newAdmin);
+
+    // verify
+    perpetuals = await tc.program.account.perpetuals.fetch(
+      tc.perpetuals.publicKey
+    );
+    expect(perpetuals.admin.toBase58()).to.equal(
+      newAdmin.publicKey.toBase58()
+    );
+    expect(perpetuals.pendingAdmin.toBase58()).to.equal(
+      PublicKey.default.toBase58()
+    );
+
+    // the new admin took over the multisig seat of the previous one
+    let multisig = await tc.program.account.multisig.fetch(
+      tc.multisig.publicKey
+    );
+    expect(multisig.numSigners).to.equal(1);
+    expect(multisig.signers[0].toBase58()).to.equal(
+      newAdmin.publicKey.toBase58()
+    );
+    await expectError(
+      tc.proposeAdmin(newAdmin.publicKey),
+      "MultisigAccountNotAuthorized"
+    );
+
+    // hand the seat back
+    await tc.proposeAdmin(tc.admin.publicKey, newAdmin);
+    await tc.acceptAdmin(tc.admin);
+    multisig = await tc.program.account.multisig.fetch(tc.multisig.publicKey);
+    expect(multisig.signers[0].toBase58()).to.equal(
+      tc.admin.publicKey.toBase58()
+    );
+  });
+
+  it("migrate", async () => {
+    // accounts created by this program version are already current
+    await expectError(tc.migratePerpetuals(), "AccountAlreadyMigrated");
+    await expectError(
+      tc.migratePool(tc.pool.publicKey),
+      "AccountAlreadyMigrated"
+    );
+    await expectError(
+      tc.migrateCustody(tc.custodies[0].custody),
+      "AccountAlreadyMigrated"
+    );
+
+    // accounts of another type are rejected before they are reallocated
+    let poolInfo = await tc.provider.connection.getAccountInfo(
+      tc.pool.publicKey
+    );
+    await expectError(
+      tc.migratePosition(tc.pool.publicKey),
+      "AccountDiscriminatorMismatch"
+    );
+    expect(
+      (await tc.provider.connection.getAccountInfo(tc.pool.publicKey)).data
+        .length
+    ).to.equal(poolInfo.data.length);
+
+    // legacy position loaded from tests/fixtures
+    let legacyPosition = new PublicKey(
+      "5nnoX7bCJUJ1bydMqcBNavhAmqeoqBMb3vDxmAc2SEaN"
+    );
+    let legacyInfo = await tc.provider.connection.getAccountInfo(
+      legacyPosition
+    );
+    expect(legacyInfo.data.length).to.equal(224);
+
+    await tc.migratePosition(legacyPosition);
+
+    // verify
+    let info = await tc.provider.connection.getAccountInfo(legacyPosition);
+    expect(info.data.length).to.equal(224 + 65);
+    let position = await tc.program.account.position.fetch(legacyPosition);
+    expect(position.version).to.equal(1);
+    expect(position.owner.toBase58()).to.equal(
+      "7VeXewgNdkKVVpUGSP3ZyTwhyZMDDeUA4tVvbqss4PQn"
+    );
+    expect(JSON.stringify(position.side)).to.equal(
+      JSON.stringify({ long: {} })
+    );
+    expect(position.price.toString()).to.equal("1000000");
+    expect(position.sizeUsd.toString()).to.equal("2000000");
+    expect(position.collateralAmount.toString()).to.equal("1000000000");
+    expect(position.lockedAmount.toString()).to.equal("2000000000");
+    expect(position.bump).to.equal(254);
+    expect(position.padding.every((x) => x.isZero())).to.be.true;
+
+    await expectError(
+      tc.migratePosition(legacyPosition),
+      "AccountAlreadyMigrated"
+    );
   });
 });
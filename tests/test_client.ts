@@ -20,6 +20,8 @@ export class TestClient {
   // pdas
   authority: { publicKey: PublicKey; bump: number };
   perpetuals: { publicKey: PublicKey; bump: number };
+  multisig: { publicKey: PublicKey; bump: number };
+  auditLog: { publicKey: PublicKey; bump: number };
   pool: { publicKey: PublicKey; bump: number };
   lpToken: { publicKey: PublicKey; bump: number };
 
@@ -56,6 +58,8 @@ export class TestClient {
     // pdas
     this.authority = await this.findProgramAddress("transfer_authority");
     this.perpetuals = await this.findProgramAddress("perpetuals");
+    this.multisig = await this.findProgramAddress("multisig");
+    this.auditLog = await this.findProgramAddress("audit_log");
     this.pool = await this.findProgramAddress("pool", "test pool");
     this.lpToken = await this.findProgramAddress("lp_token_mint", [
       this.pool.publicKey,
@@ -192,15 +196,32 @@ export class TestClient {
         mint.publicKey,
       ])
     ).publicKey;
+    let insuranceFundTokenAccount = (
+      await this.findProgramAddress("insurance_fund_token_account", [
+        this.pool.publicKey,
+        mint.publicKey,
+      ])
+    ).publicKey;
     return {
       mint,
       tokenAccount,
       oracleAccount,
       custody,
+      insuranceFundTokenAccount,
       decimals,
     };
   };
 
+  getPendingChange = async (custody, depositCaps = false) => {
+    return (
+      await this.findProgramAddress("pending_change", [
+        this.pool.publicKey,
+        custody.custody,
+        [depositCaps ? 1 : 0],
+      ])
+    ).publicKey;
+  };
+
   findProgramAddress = async (label: string, extraSeeds = null) => {
     let seeds = [Buffer.from(anchor.utils.bytes.utf8.encode(label))];
     if (extraSeeds) {
@@ -260,6 +281,33 @@ export class TestClient {
     return utcMilllisecondsSinceEpoch / 1000;
   }
 
+  getChainTime = async () => {
+    return this.provider.connection.getBlockTime(
+      await this.provider.connection.getSlot()
+    );
+  };
+
+  getOrderAccount = async (
+    user,
+    custody,
+    side: PositionSide,
+    orderId: number = 0
+  ) => {
+    let order = (
+      await this.findProgramAddress("order", [
+        user.wallet.publicKey,
+        this.pool.publicKey,
+        custody.custody,
+        [side === "long" ? 1 : 2],
+        new BN(orderId).toArray("le", 8),
+      ])
+    ).publicKey;
+    let tokenAccount = (
+      await this.findProgramAddress("order_token_account", [order])
+    ).publicKey;
+    return { order, tokenAccount };
+  };
+
   toTokenAmount(uiAmount: number, decimals: number) {
     return new BN(uiAmount * 10 ** decimals);
   }
@@ -270,14 +318,19 @@ export class TestClient {
   init = async () => {
     try {
       await this.program.methods
-        .init({})
+        .init({ minSignatures: 1 })
         .accounts({
           admin: this.admin.publicKey,
           transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
+          multisig: this.multisig.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
         })
+        .remainingAccounts([
+          { isSigner: false, isWritable: false, pubkey: this.admin.publicKey },
+        ])
         .signers([this.admin])
         .rpc();
     } catch (err) {
@@ -286,19 +339,26 @@ export class TestClient {
     }
   };
 
-  addPool = async (name) => {
+  addPool = async (
+    name,
+    maxAumUsd: typeof BN,
+    pool = this.pool,
+    lpToken = this.lpToken
+  ) => {
     try {
       await this.program.methods
-        .addPool({ name })
+        .addPool({ name, maxAumUsd })
         .accounts({
           admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
           transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          lpTokenMint: this.lpToken.publicKey,
+          pool: pool.publicKey,
+          lpTokenMint: lpToken.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY,
+          auditLog: this.auditLog.publicKey,
         })
         .signers([this.admin])
         .rpc();
@@ -307,6 +367,10 @@ export class TestClient {
       throw err;
     }
 
+    if (pool !== this.pool) {
+      return;
+    }
+
     // set lp token account
     let tokenAccount = await spl.getOrCreateAssociatedTokenAccount(
       this.provider.connection,
@@ -317,24 +381,37 @@ export class TestClient {
     this.user.lpTokenAccount = tokenAccount.address;
   };
 
-  addToken = async (custody, oracleConfig, pricingConfig) => {
+  addToken = async (
+    custody,
+    isStable,
+    oracleConfig,
+    pricingConfig,
+    feesConfig,
+    maxAmount: typeof BN
+  ) => {
     try {
       await this.program.methods
         .addToken({
+          isStable,
           oracle: oracleConfig,
           pricing: pricingConfig,
+          fees: feesConfig,
+          maxAmount,
         })
         .accounts({
           admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
           transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
           pool: this.pool.publicKey,
           custody: custody.custody,
           custodyTokenAccount: custody.tokenAccount,
+          insuranceFundTokenAccount: custody.insuranceFundTokenAccount,
           custodyTokenMint: custody.mint.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY,
+          auditLog: this.auditLog.publicKey,
         })
         .signers([this.admin])
         .rpc();
@@ -344,21 +421,59 @@ export class TestClient {
     }
   };
 
-  setTestOraclePrice = async (price: typeof BN, custody) => {
+  removePool = async (pool, lpToken) => {
     try {
       await this.program.methods
-        .setTestOraclePrice({
-          price,
-          expo: -3,
-          conf: new BN(0),
-          publishTime: new BN(this.getTime()),
+        .removePool({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: pool.publicKey,
+          lpTokenMint: lpToken.publicKey,
+          auditLog: this.auditLog.publicKey,
         })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  removeToken = async (custody) => {
+    try {
+      await this.program.methods
+        .removeToken({})
         .accounts({
           admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
           pool: this.pool.publicKey,
           custody: custody.custody,
-          oracleAccount: custody.oracleAccount,
+          custodyTokenAccount: custody.tokenAccount,
+          insuranceFundTokenAccount: custody.insuranceFundTokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  migratePerpetuals = async () => {
+    try {
+      await this.program.methods
+        .migratePerpetuals({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          auditLog: this.auditLog.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .signers([this.admin])
@@ -369,37 +484,39 @@ export class TestClient {
     }
   };
 
-  swap = async (
-    amountIn: typeof BN,
-    minAmountOut: typeof BN,
-    user,
-    fundingAccount: PublicKey,
-    receivingAccount: PublicKey,
-    custodyIn,
-    custodyOut
-  ) => {
+  migratePool = async (pool: PublicKey) => {
     try {
       await this.program.methods
-        .swap({
-          amountIn,
-          minAmountOut,
+        .migratePool({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool,
+          auditLog: this.auditLog.publicKey,
+          systemProgram: SystemProgram.programId,
         })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  migrateCustody = async (custody: PublicKey) => {
+    try {
+      await this.program.methods
+        .migrateCustody({})
         .accounts({
-          owner: user.wallet.publicKey,
-          fundingAccount: fundingAccount,
-          receivingAccount,
-          transferAuthority: this.authority.publicKey,
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          receivingCustody: custodyIn.custody,
-          receivingCustodyOracleAccount: custodyIn.oracleAccount,
-          receivingCustodyTokenAccount: custodyIn.tokenAccount,
-          dispensingCustody: custodyOut.custody,
-          dispensingCustodyOracleAccount: custodyOut.oracleAccount,
-          dispensingCustodyTokenAccount: custodyOut.tokenAccount,
-          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          custody,
+          auditLog: this.auditLog.publicKey,
+          systemProgram: SystemProgram.programId,
         })
-        .signers([user.wallet])
+        .signers([this.admin])
         .rpc();
     } catch (err) {
       console.log(err);
@@ -407,32 +524,42 @@ export class TestClient {
     }
   };
 
-  addLiquidity = async (
-    amount: typeof BN,
-    user,
-    fundingAccount: PublicKey,
-    custody
+  migratePosition = async (position: PublicKey) => {
+    try {
+      await this.program.methods
+        .migratePosition({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          position,
+          auditLog: this.auditLog.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  proposeAdmin = async (
+    newAdmin: PublicKey,
+    signer: Keypair = this.admin
   ) => {
     try {
       await this.program.methods
-        .addLiquidity({
-          amount,
+        .proposeAdmin({
+          newAdmin,
         })
         .accounts({
-          owner: user.wallet.publicKey,
-          fundingAccount,
-          lpTokenAccount: user.lpTokenAccount,
-          transferAuthority: this.authority.publicKey,
+          admin: signer.publicKey,
+          multisig: this.multisig.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          custody: custody.custody,
-          custodyOracleAccount: custody.oracleAccount,
-          custodyTokenAccount: custody.tokenAccount,
-          lpTokenMint: this.lpToken.publicKey,
-          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
         })
-        .remainingAccounts(this.custodyMetas)
-        .signers([user.wallet])
+        .signers([signer])
         .rpc();
     } catch (err) {
       console.log(err);
@@ -440,32 +567,35 @@ export class TestClient {
     }
   };
 
-  removeLiquidity = async (
-    lpAmount: typeof BN,
-    user,
-    receivingAccount: PublicKey,
-    custody
-  ) => {
+  acceptAdmin = async (newAdmin: Keypair) => {
     try {
       await this.program.methods
-        .removeLiquidity({
-          lpAmount,
+        .acceptAdmin({})
+        .accounts({
+          pendingAdmin: newAdmin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          auditLog: this.auditLog.publicKey,
         })
+        .signers([newAdmin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  cancelAdminProposal = async () => {
+    try {
+      await this.program.methods
+        .cancelAdminProposal({})
         .accounts({
-          owner: user.wallet.publicKey,
-          receivingAccount: receivingAccount,
-          lpTokenAccount: user.lpTokenAccount,
-          transferAuthority: this.authority.publicKey,
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          custody: custody.custody,
-          custodyOracleAccount: custody.oracleAccount,
-          custodyTokenAccount: custody.tokenAccount,
-          lpTokenMint: this.lpToken.publicKey,
-          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
         })
-        .remainingAccounts(this.custodyMetas)
-        .signers([user.wallet])
+        .signers([this.admin])
         .rpc();
     } catch (err) {
       console.log(err);
@@ -473,38 +603,29 @@ export class TestClient {
     }
   };
 
-  openPosition = async (
-    price: typeof BN,
-    collateral: typeof BN,
-    size: typeof BN,
-    side: PositionSide,
-    user,
-    fundingAccount: PublicKey,
-    positionAccount: PublicKey,
-    custody
+  setAdminSigners = async (
+    signers: PublicKey[],
+    minSignatures: number,
+    signer: Keypair = this.admin
   ) => {
     try {
       await this.program.methods
-        .openPosition({
-          price,
-          collateral,
-          size,
-          side: side === "long" ? { long: {} } : { short: {} },
+        .setAdminSigners({
+          minSignatures,
         })
         .accounts({
-          owner: user.wallet.publicKey,
-          fundingAccount,
-          transferAuthority: this.authority.publicKey,
-          perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          position: positionAccount,
-          custody: custody.custody,
-          custodyOracleAccount: custody.oracleAccount,
-          custodyTokenAccount: custody.tokenAccount,
-          systemProgram: SystemProgram.programId,
-          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          admin: signer.publicKey,
+          multisig: this.multisig.publicKey,
+          auditLog: this.auditLog.publicKey,
         })
-        .signers([user.wallet])
+        .remainingAccounts(
+          signers.map((pubkey) => ({
+            isSigner: false,
+            isWritable: false,
+            pubkey,
+          }))
+        )
+        .signers([signer])
         .rpc();
     } catch (err) {
       console.log(err);
@@ -512,33 +633,26 @@ export class TestClient {
     }
   };
 
-  closePosition = async (
-    price: typeof BN,
-    size: typeof BN,
-    user,
-    receivingAccount,
-    positionAccount,
-    custody
+  setPermissions = async (
+    permissions,
+    target?: PublicKey,
+    signer: Keypair = this.admin
   ) => {
     try {
       await this.program.methods
-        .closePosition({
-          price: price,
-          size: size,
+        .setPermissions({
+          permissions,
         })
         .accounts({
-          owner: user.wallet.publicKey,
-          receivingAccount,
-          transferAuthority: this.authority.publicKey,
+          admin: signer.publicKey,
+          multisig: this.multisig.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          pool: this.pool.publicKey,
-          position: positionAccount,
-          custody: custody.custody,
-          custodyOracleAccount: custody.oracleAccount,
-          custodyTokenAccount: custody.tokenAccount,
-          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
         })
-        .signers([user.wallet])
+        .remainingAccounts(
+          target ? [{ isSigner: false, isWritable: true, pubkey: target }] : []
+        )
+        .signers([signer])
         .rpc();
     } catch (err) {
       console.log(err);
@@ -546,27 +660,800 @@ export class TestClient {
     }
   };
 
-  liquidate = async (
-    user,
-    receivingAccount: PublicKey,
-    rewardReceivingAccount: PublicKey,
-    positionAccount: PublicKey,
-    custody
+  setRole = async (role, account: PublicKey) => {
+    try {
+      await this.program.methods
+        .setRole({
+          role,
+          account,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  setCustodyConfig = async (
+    custody,
+    isStable,
+    oracleConfig,
+    pricingConfig,
+    feesConfig,
+    signer: Keypair = this.admin
   ) => {
     try {
       await this.program.methods
-        .liquidate({})
+        .setCustodyConfig({
+          isStable,
+          oracle: oracleConfig,
+          pricing: pricingConfig,
+          fees: feesConfig,
+        })
         .accounts({
-          signer: user.wallet.publicKey,
-          receivingAccount,
-          rewardReceivingAccount,
-          transferAuthority: this.authority.publicKey,
+          admin: signer.publicKey,
+          multisig: this.multisig.publicKey,
           perpetuals: this.perpetuals.publicKey,
           pool: this.pool.publicKey,
-          position: positionAccount,
           custody: custody.custody,
-          custodyOracleAccount: custody.oracleAccount,
-          custodyTokenAccount: custody.tokenAccount,
+          pendingChange: await this.getPendingChange(custody),
+          systemProgram: SystemProgram.programId,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  applyPendingChange = async (custody, depositCaps = false) => {
+    let pendingChange = await this.getPendingChange(custody, depositCaps);
+    let pendingChangeData =
+      await this.program.account.pendingChange.fetch(pendingChange);
+    try {
+      await this.program.methods
+        .applyPendingChange({})
+        .accounts({
+          executor: this.provider.wallet.publicKey,
+          payer: pendingChangeData.payer,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          pendingChange,
+          auditLog: this.auditLog.publicKey,
+        })
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  cancelPendingChange = async (custody, depositCaps = false) => {
+    let pendingChange = await this.getPendingChange(custody, depositCaps);
+    let pendingChangeData =
+      await this.program.account.pendingChange.fetch(pendingChange);
+    try {
+      await this.program.methods
+        .cancelPendingChange({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          payer: pendingChangeData.payer,
+          perpetuals: this.perpetuals.publicKey,
+          pendingChange,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  setTimelockDelay = async (timelockDelaySec: typeof BN) => {
+    try {
+      await this.program.methods
+        .setTimelockDelay({
+          timelockDelaySec,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  setDepositCaps = async (
+    maxAumUsd: typeof BN,
+    maxAmount: typeof BN,
+    custody,
+    signer: Keypair = this.admin
+  ) => {
+    try {
+      await this.program.methods
+        .setDepositCaps({
+          maxAumUsd,
+          maxAmount,
+        })
+        .accounts({
+          parameterManager: signer.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          pendingChange: await this.getPendingChange(custody, true),
+          auditLog: this.auditLog.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([signer])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  topUpInsuranceFund = async (
+    amount: typeof BN,
+    fundingAccount: PublicKey,
+    custody
+  ) => {
+    try {
+      await this.program.methods
+        .topUpInsuranceFund({
+          amount,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          fundingAccount,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          insuranceFundTokenAccount: custody.insuranceFundTokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  withdrawInsuranceFund = async (
+    amount: typeof BN,
+    receivingAccount: PublicKey,
+    custody,
+    signer: Keypair = this.admin
+  ) => {
+    try {
+      await this.program.methods
+        .withdrawInsuranceFund({
+          amount,
+        })
+        .accounts({
+          admin: signer.publicKey,
+          multisig: this.multisig.publicKey,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          insuranceFundTokenAccount: custody.insuranceFundTokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          auditLog: this.auditLog.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  setTestOraclePrice = async (price: typeof BN, custody) => {
+    try {
+      await this.program.methods
+        .setTestOraclePrice({
+          price,
+          expo: -3,
+          conf: new BN(0),
+          publishTime: new BN(this.getTime()),
+        })
+        .accounts({
+          oracleUpdater: this.admin.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          oracleAccount: custody.oracleAccount,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  swap = async (
+    amountIn: typeof BN,
+    minAmountOut: typeof BN,
+    user,
+    fundingAccount: PublicKey,
+    receivingAccount: PublicKey,
+    custodyIn,
+    custodyOut,
+    deadline: typeof BN = null
+  ) => {
+    try {
+      await this.program.methods
+        .swap({
+          amountIn,
+          minAmountOut,
+          deadline,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          fundingAccount: fundingAccount,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          receivingCustody: custodyIn.custody,
+          receivingCustodyOracleAccount: custodyIn.oracleAccount,
+          receivingCustodyTokenAccount: custodyIn.tokenAccount,
+          dispensingCustody: custodyOut.custody,
+          dispensingCustodyOracleAccount: custodyOut.oracleAccount,
+          dispensingCustodyTokenAccount: custodyOut.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  addLiquidity = async (
+    amount: typeof BN,
+    user,
+    fundingAccount: PublicKey,
+    custody
+  ) => {
+    try {
+      await this.program.methods
+        .addLiquidity({
+          amount,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          fundingAccount,
+          lpTokenAccount: user.lpTokenAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          custodyTokenAccount: custody.tokenAccount,
+          lpTokenMint: this.lpToken.publicKey,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(this.custodyMetas)
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  removeLiquidity = async (
+    lpAmount: typeof BN,
+    user,
+    receivingAccount: PublicKey,
+    custody,
+    allowPartial = false
+  ) => {
+    try {
+      await this.program.methods
+        .removeLiquidity({
+          lpAmount,
+          allowPartial,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          receivingAccount: receivingAccount,
+          lpTokenAccount: user.lpTokenAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          custodyTokenAccount: custody.tokenAccount,
+          lpTokenMint: this.lpToken.publicKey,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(this.custodyMetas)
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  openPosition = async (
+    price: typeof BN,
+    collateral: typeof BN,
+    size: typeof BN,
+    side: PositionSide,
+    user,
+    fundingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .openPosition({
+          price,
+          collateral,
+          size,
+          side: side === "long" ? { long: {} } : { short: {} },
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          fundingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          insuranceFundTokenAccount: collateralCustody.insuranceFundTokenAccount,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  closePosition = async (
+    price: typeof BN,
+    sizeUsd: typeof BN,
+    user,
+    receivingAccount,
+    positionAccount,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .closePosition({
+          price,
+          sizeUsd,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          insuranceFundTokenAccount: collateralCustody.insuranceFundTokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  addCollateral = async (
+    collateral: typeof BN,
+    user,
+    fundingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .addCollateral({
+          collateral,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          fundingAccount,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  removeCollateral = async (
+    collateralUsd: typeof BN,
+    user,
+    receivingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .removeCollateral({
+          collateralUsd,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  createOrder = async (
+    triggerPrice: typeof BN,
+    collateral: typeof BN,
+    size: typeof BN,
+    side: PositionSide,
+    executionFee: typeof BN,
+    expirationTime: typeof BN,
+    user,
+    fundingAccount: PublicKey,
+    custody,
+    collateralCustody,
+    orderId: number = 0
+  ) => {
+    let order = await this.getOrderAccount(user, custody, side, orderId);
+    try {
+      await this.program.methods
+        .createOrder({
+          orderId: new BN(orderId),
+          triggerPrice,
+          collateral,
+          size,
+          side: side === "long" ? { long: {} } : { short: {} },
+          executionFee,
+          expirationTime,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          fundingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          order: order.order,
+          orderTokenAccount: order.tokenAccount,
+          custody: custody.custody,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyMint: collateralCustody.mint.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  executeOrder = async (
+    keeper,
+    feeReceivingAccount: PublicKey,
+    user,
+    side: PositionSide,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody,
+    orderId: number = 0
+  ) => {
+    let order = await this.getOrderAccount(user, custody, side, orderId);
+    try {
+      await this.program.methods
+        .executeOrder({})
+        .accounts({
+          keeper: keeper.wallet.publicKey,
+          feeReceivingAccount,
+          owner: user.wallet.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          order: order.order,
+          orderTokenAccount: order.tokenAccount,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          insuranceFundTokenAccount: collateralCustody.insuranceFundTokenAccount,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  cancelOrder = async (
+    user,
+    receivingAccount: PublicKey,
+    side: PositionSide,
+    custody,
+    orderId: number = 0
+  ) => {
+    let order = await this.getOrderAccount(user, custody, side, orderId);
+    try {
+      await this.program.methods
+        .cancelOrder({ deadline: null })
+        .accounts({
+          owner: user.wallet.publicKey,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          order: order.order,
+          orderTokenAccount: order.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  setTrigger = async (
+    stopLossPrice: typeof BN,
+    takeProfitPrice: typeof BN,
+    sizeUsd: typeof BN,
+    executionFeeUsd: typeof BN,
+    user,
+    positionAccount: PublicKey
+  ) => {
+    try {
+      await this.program.methods
+        .setTrigger({
+          stopLossPrice,
+          takeProfitPrice,
+          sizeUsd,
+          executionFeeUsd,
+          deadline: null,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+        })
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  executeTrigger = async (
+    executor,
+    feeReceivingAccount: PublicKey,
+    user,
+    receivingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .executeTrigger({})
+        .accounts({
+          executor: executor.wallet.publicKey,
+          feeReceivingAccount,
+          receivingAccount,
+          owner: user.wallet.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          insuranceFundTokenAccount: collateralCustody.insuranceFundTokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .signers([executor.wallet])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  autoDeleverage = async (
+    user,
+    receivingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody,
+    queue: PublicKey[]
+  ) => {
+    try {
+      await this.program.methods
+        .autoDeleverage({})
+        .accounts({
+          keeper: this.admin.publicKey,
+          receivingAccount,
+          owner: user.wallet.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          queue.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+        )
+        .signers([this.admin])
+        .rpc();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  getAuditLog = async (start: number, count: number) => {
+    try {
+      return await this.program.methods
+        .getAuditLog({
+          start: new BN(start),
+          count,
+        })
+        .accounts({
+          auditLog: this.auditLog.publicKey,
+        })
+        .view();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  getAdlQueue = async (
+    custody,
+    collateralCustody,
+    side: PositionSide,
+    queue: PublicKey[]
+  ) => {
+    try {
+      return await this.program.methods
+        .getAdlQueue({
+          side: side === "long" ? { long: {} } : { short: {} },
+        })
+        .accounts({
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+        })
+        .remainingAccounts(
+          queue.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+        )
+        .view();
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  liquidate = async (
+    user,
+    receivingAccount: PublicKey,
+    rewardReceivingAccount: PublicKey,
+    positionAccount: PublicKey,
+    custody,
+    collateralCustody
+  ) => {
+    try {
+      await this.program.methods
+        .liquidate({})
+        .accounts({
+          signer: user.wallet.publicKey,
+          receivingAccount,
+          rewardReceivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          position: positionAccount,
+          custody: custody.custody,
+          custodyOracleAccount: custody.oracleAccount,
+          collateralCustody: collateralCustody.custody,
+          collateralCustodyOracleAccount: collateralCustody.oracleAccount,
+          collateralCustodyTokenAccount: collateralCustody.tokenAccount,
+          insuranceFundTokenAccount: collateralCustody.insuranceFundTokenAccount,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .signers([user.wallet])